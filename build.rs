@@ -1,3 +1,3 @@
 fn main() {
     println!("cargo:rustc-link-search=lib");
-}
\ No newline at end of file
+}
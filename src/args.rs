@@ -1,17 +1,42 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use video_presenter::{
+    music::MusicConfig,
+    presenter::Config,
+    project::Project,
+    transition::{Transition, TransitionKind},
+};
 
 #[derive(Parser)]
-#[command(author, version, about)]
+#[command(
+    author,
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Subcommands>,
+
     /// Path of the video file to play.
-    #[arg()]
-    pub video: PathBuf,
+    #[arg(required_unless_present_any = ["playlist", "project", "list_audio_devices"])]
+    pub video: Option<PathBuf>,
 
     /// Marker file (csv or txt) exported from premiere pro.
-    #[arg()]
-    pub markers: PathBuf,
+    #[arg(required_unless_present_any = ["playlist", "project", "list_audio_devices"])]
+    pub markers: Option<PathBuf>,
+
+    /// Manifest of several video and marker files to play as one presentation, instead of a single video.
+    /// Each line is `<video>, <markers>`, relative to the manifest.
+    #[arg(short, long, conflicts_with_all = ["video", "markers"])]
+    pub playlist: Option<PathBuf>,
+
+    /// Project file (TOML) with the media, cues and settings of a presentation, instead of passing them all here.
+    /// Any other options given are applied on top of it.
+    #[arg(long, conflicts_with_all = ["video", "markers", "playlist"])]
+    pub project: Option<PathBuf>,
 
     /// Passes a setting value directly to mpv.
     #[arg(short, long, value_parser = parse_setting)]
@@ -22,6 +47,193 @@ pub struct Args {
     /// Default is false.
     #[arg(short, long)]
     pub audio: bool,
+
+    /// The audio output to play to, by its mpv name (see `--list-audio-devices`).
+    #[arg(long, value_name = "NAME")]
+    pub audio_device: Option<String>,
+
+    /// Prints the audio outputs that can be used with `--audio-device`, then exits.
+    #[arg(long)]
+    pub list_audio_devices: bool,
+
+    /// Seconds to fade the audio out before each cue and back in when resuming, so pauses don't click or pop.
+    /// Only used with `--audio`.
+    #[arg(short, long)]
+    pub fade: Option<f64>,
+
+    /// Audio file to loop in the background, which keeps playing while the video is held at a cue.
+    #[arg(long)]
+    pub music: Option<PathBuf>,
+
+    /// Volume of the background music, as a percentage.
+    #[arg(long, value_name = "PERCENT")]
+    pub music_volume: Option<f64>,
+
+    /// Volume of the background music while the video is playing, as a percentage.
+    #[arg(long, value_name = "PERCENT")]
+    pub music_duck: Option<f64>,
+
+    /// Transition to hide the cut when jumping between cues (`fade` through black or `crossfade`).
+    #[arg(long, value_parser = parse_transition)]
+    pub transition: Option<TransitionKind>,
+
+    /// Length of the transition in seconds.
+    #[arg(long, value_name = "SECONDS", requires = "transition")]
+    pub transition_time: Option<f64>,
+
+    /// Makes going back play backwards to the previous cue, so animations visibly un-build, rather than jumping to it.
+    #[arg(long)]
+    pub rewind: bool,
+
+    /// Runs without a window or any output, reading commands from stdin.
+    /// Used for automated testing of presentations.
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Timing script to play the presentation unattended with.
+    /// Each line is `<cue> <seconds>`, the time to wait at that cue before advancing.
+    #[arg(short, long)]
+    pub script: Option<PathBuf>,
+
+    /// Records how long is spent at each cue, writing a report to `<REHEARSE>.json` and `<REHEARSE>.csv` on exit.
+    #[arg(short, long)]
+    pub rehearse: Option<PathBuf>,
+
+    /// Timing script with the target time for each cue, to compare against when rehearsing.
+    #[arg(short, long, requires = "rehearse")]
+    pub targets: Option<PathBuf>,
+
+    /// Appends a JSON lines log of everything that happens in the session to this file.
+    #[arg(short, long)]
+    pub log: Option<PathBuf>,
+
+    /// Jumps back to the cue the last session was left at, without asking.
+    #[arg(long)]
+    pub resume: bool,
+}
+
+impl Args {
+    /// Gets the background music settings, from the arguments or the project.
+    pub fn music(&self, project: Option<&Project>) -> Option<MusicConfig> {
+        let mut music = match &self.music {
+            Some(file) => MusicConfig {
+                file: file.clone(),
+                volume: MusicConfig::default_volume(),
+                duck: MusicConfig::default_duck(),
+            },
+            None => project?.music.clone()?,
+        };
+
+        music.volume = self.music_volume.unwrap_or(music.volume);
+        music.duck = self.music_duck.unwrap_or(music.duck);
+        Some(music)
+    }
+
+    /// Gets the transition, from the arguments or the project.
+    fn transition(&self, project: Option<Transition>) -> Option<Transition> {
+        let mut transition = match self.transition {
+            Some(kind) => Transition {
+                kind,
+                duration: Transition::default_duration(),
+            },
+            None => project?,
+        };
+
+        transition.duration = self.transition_time.unwrap_or(transition.duration);
+        Some(transition)
+    }
+
+    /// Converts the arguments into a presenter config, rendering into the given window (if any).
+    /// If a project was loaded, the arguments are applied on top of its settings.
+    pub fn into_config(self, project: Option<&Project>, wid: Option<u64>) -> Config {
+        let transition = self.transition(project.and_then(|x| x.transition));
+        let Some(project) = project else {
+            return Config {
+                video: self.video.unwrap_or_default(),
+                markers: self.markers.unwrap_or_default(),
+                playlist: self.playlist,
+                mpv_settings: self.mpv_setting,
+                audio: self.audio,
+                audio_device: self.audio_device,
+                fps: None,
+                transition,
+                rewind: self.rewind,
+                wid,
+                headless: self.headless,
+                log: self.log,
+            };
+        };
+
+        let mut config = project.config();
+        config.transition = transition;
+        config.rewind |= self.rewind;
+        config.mpv_settings.extend(self.mpv_setting);
+        config.audio |= self.audio;
+        config.audio_device = self.audio_device.or(config.audio_device);
+        config.wid = wid;
+        config.headless = self.headless;
+        config.log = self.log;
+        config
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Subcommands {
+    /// Saves the frame at every cue as a PNG, optionally bundling them into a PDF handout, then exits.
+    ///
+    /// This runs mpv headlessly, so it works without a display.
+    ExportStills(ExportArgs),
+}
+
+#[derive(clap::Args)]
+pub struct ExportArgs {
+    /// Path of the video file to export.
+    #[arg(required_unless_present_any = ["playlist", "project"])]
+    pub video: Option<PathBuf>,
+
+    /// Marker file (csv or txt) exported from premiere pro.
+    #[arg(required_unless_present_any = ["playlist", "project"])]
+    pub markers: Option<PathBuf>,
+
+    /// Manifest of several video and marker files to export as one presentation, instead of a single video.
+    #[arg(short, long, conflicts_with_all = ["video", "markers"])]
+    pub playlist: Option<PathBuf>,
+
+    /// Project file (TOML) with the media and cues of a presentation.
+    #[arg(long, conflicts_with_all = ["video", "markers", "playlist"])]
+    pub project: Option<PathBuf>,
+
+    /// Directory to save the images to, as `cue-<N>.png`.
+    #[arg(short, long, value_name = "DIR", default_value = "stills")]
+    pub out: PathBuf,
+
+    /// Also writes a PDF with a page for every cue, captioned with the marker's name and notes.
+    #[arg(long, value_name = "FILE")]
+    pub pdf: Option<PathBuf>,
+}
+
+impl ExportArgs {
+    /// Gets the config to load the playlist from, from the arguments or the project.
+    pub fn config(&self, project: Option<&Project>) -> Config {
+        match project {
+            Some(project) => project.config(),
+            None => Config {
+                video: self.video.clone().unwrap_or_default(),
+                markers: self.markers.clone().unwrap_or_default(),
+                playlist: self.playlist.clone(),
+                ..Config::default()
+            },
+        }
+    }
+}
+
+/// Parses the kind of transition from its name.
+fn parse_transition(raw: &str) -> Result<TransitionKind, String> {
+    match raw {
+        "fade" => Ok(TransitionKind::Fade),
+        "crossfade" => Ok(TransitionKind::Crossfade),
+        _ => Err("expected `fade` or `crossfade`".to_owned()),
+    }
 }
 
 /// Parses a mpv setting into the key and value.
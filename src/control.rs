@@ -0,0 +1,77 @@
+//! Control channel for headless mode.
+//! Commands are read one per line, see [`Command::from_str`] for the presentation commands.
+//! On top of those, `status` prints the current state, `wait` blocks until playback stops and `quit` exits.
+
+use std::{
+    io::{self, BufRead},
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use video_presenter::{
+    player::Player,
+    presenter::Presenter,
+    state::{Command, State},
+};
+
+/// Reads commands from `input` until it ends or `quit` is received.
+pub fn run<P: Player + 'static>(app: &Presenter<P>, input: impl BufRead) -> io::Result<()> {
+    // Print cue changes as they happen, so they show up in CI logs
+    let events = app.subscribe();
+    thread::spawn(move || {
+        for e in events {
+            println!("cue from={} to={} reached={}", e.from, e.to, e.reached);
+        }
+    });
+
+    for line in input.lines() {
+        let line = line?;
+        match line.trim() {
+            "" => {}
+            "quit" => break,
+            "status" => print_status(app),
+            "wait" => {
+                wait(app);
+                print_status(app);
+            }
+            cmd => match Command::from_str(cmd) {
+                Ok(cmd) => app.send(cmd),
+                Err(e) => eprintln!("[-] {e}"),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Blocks until the presentation has loaded and is no longer playing.
+fn wait<P: Player + 'static>(app: &Presenter<P>) {
+    while let Some(status) = app.status() {
+        let moving = matches!(
+            status.state,
+            State::Playing { .. } | State::Rewinding { .. }
+        );
+        if !moving && status.state != State::Loading {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Prints the state, cue and position of the presentation on one line.
+fn print_status<P: Player + 'static>(app: &Presenter<P>) {
+    let Some(status) = app.status() else {
+        return;
+    };
+
+    let time = app.player().position().unwrap_or_default();
+    println!(
+        "status cue={} time={:.3} frame={} state={:?}",
+        status.cue,
+        time,
+        (time * status.fps).round(),
+        status.state
+    );
+}
@@ -1,55 +1,57 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    fs, mem,
+    ops::{Deref, DerefMut},
+    path::Path,
+    result,
+    str::FromStr,
+    time::Duration,
+};
 
-use anyhow::Result;
-
-use crate::time::{time, Time};
+use crate::{
+    error::{CueError, CueErrorKind, Error, Result},
+    time::{time, Time},
+};
 
 /// A collection of time cues.
 /// Guaranteed to be sorted with the first cue being 0.
+#[derive(Debug, Clone, Default)]
 pub struct Cues {
     inner: Vec<Time>,
+    /// Target duration of the section following each cue, from the marker's `budget=` tag.
+    budgets: Vec<Option<Duration>>,
+    /// Playback speed of the section following each cue, from the marker's `speed=` tag.
+    speeds: Vec<Option<f64>>,
+    /// Name of each cue's marker, without any tags.
+    names: Vec<String>,
+    /// Description of each cue's marker, without any tags.
+    notes: Vec<String>,
 }
 
 impl Cues {
-    /// Load cues from a file.
-    /// This file can be created from Adobe Premiere Pro when [exporting markers](https://community.adobe.com/t5/premiere-pro-discussions/how-to-export-clip-markers/td-p/10337163).
-    /// If using After Effects, you can use [Marker Batch Editor Script](https://aescripts.com/marker-batch-editor) with this output formatter: `,,[time],[time],[markerDuration],Cue Point\n`.
-    ///
-    /// If you use something else, like Resolve, Im sure you can figure it out.
-    /// Just make sure that:
-    /// - The file is tab or comma separated
-    /// - Index 2 and 3 are the time of the marker (ex: HH:MM:SS:FF)
-    /// - Index 5 is the marker type (Must contain 'Cue Point')
-    pub fn from_str(contents: &str) -> Result<Self> {
-        let mut inner = Vec::new();
-        let header_present = contents.chars().next().unwrap().is_alphabetic();
-
-        for (i, line) in contents.lines().skip(header_present as usize).enumerate() {
-            let parts = line.split(['\t', ',']).collect::<Vec<_>>();
-
-            if !parts[5].contains("Cue Point") {
-                eprintln!(
-                    "[WARN] Skipping marker {} because it is not a 'Cue Point'",
-                    i + 1
-                );
-                continue;
-            }
+    /// Load cues from a file on disk, see [`Cues::from_str`] for the format.
+    /// Any parse errors will include the path of the file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
 
-            let start = parts[2].parse::<Time>()?;
-            let end = parts[3].parse::<Time>()?;
-            if start != end {
-                eprintln!(
-                    "[WARN] Skipping marker {} because it has a nob-zero duration",
-                    i + 1
-                );
-                continue;
-            }
+        Self::from_str(&contents).map_err(|e| match e {
+            Error::Cues { errors, .. } => Error::Cues {
+                file: Some(path.to_owned()),
+                errors,
+            },
+            e => e,
+        })
+    }
 
-            inner.push(start);
+    /// A single cue at the very start, for a still image.
+    pub fn still() -> Self {
+        Self {
+            inner: vec![time!(00:00:00:00)],
+            budgets: vec![None],
+            speeds: vec![None],
+            names: vec![String::new()],
+            notes: vec![String::new()],
         }
-
-        inner.sort();
-        Ok(Self { inner })
     }
 
     /// Gets the number of cues.
@@ -57,6 +59,39 @@ impl Cues {
         self.inner.len()
     }
 
+    /// Checks if there are no cues.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Gets the planned duration of the section starting at the given cue, if it has one.
+    /// Cue 0 (the start of the video) never has a budget, as it has no marker.
+    pub fn budget(&self, idx: usize) -> Option<Duration> {
+        *self.budgets.get(idx.checked_sub(1)?)?
+    }
+
+    /// Checks if any cue has a budget.
+    pub fn has_budgets(&self) -> bool {
+        self.budgets.iter().any(Option::is_some)
+    }
+
+    /// Gets the playback speed of the section starting at the given cue, if it has one.
+    pub fn speed(&self, idx: usize) -> Option<f64> {
+        *self.speeds.get(idx.checked_sub(1)?)?
+    }
+
+    /// Gets the name of the marker for the given cue, if it has one.
+    pub fn name(&self, idx: usize) -> Option<&str> {
+        let name = self.names.get(idx.checked_sub(1)?)?;
+        (!name.is_empty()).then_some(name.as_str())
+    }
+
+    /// Gets the description of the marker for the given cue, if it has one.
+    pub fn notes(&self, idx: usize) -> Option<&str> {
+        let notes = self.notes.get(idx.checked_sub(1)?)?;
+        (!notes.is_empty()).then_some(notes.as_str())
+    }
+
     /// Uses the current time (in seconds) and the fps to get the current cue.
     /// If not exactly on a cue, it will return the last cue.
     /// If the time is before the first cue, it will return 0 and if the time is after the last cue, it will return [`Time::END`].
@@ -85,6 +120,163 @@ impl Cues {
     }
 }
 
+impl FromStr for Cues {
+    type Err = Error;
+
+    /// Load cues from a file.
+    /// This file can be created from Adobe Premiere Pro when [exporting markers](https://community.adobe.com/t5/premiere-pro-discussions/how-to-export-clip-markers/td-p/10337163).
+    /// If using After Effects, you can use [Marker Batch Editor Script](https://aescripts.com/marker-batch-editor) with this output formatter: `,,[time],[time],[markerDuration],Cue Point\n`.
+    ///
+    /// If you use something else, like Resolve, Im sure you can figure it out.
+    /// Just make sure that:
+    /// - The file is tab or comma separated
+    /// - Index 2 and 3 are the time of the marker (ex: HH:MM:SS:FF)
+    /// - Index 5 is the marker type (Must contain 'Cue Point')
+    /// - Index 0 and 1 are the marker name and description (notes), which are optional
+    ///
+    /// A cue can optionally have a time budget for the section that follows it, by putting `budget=M:SS` (or `budget=<seconds>`) in the marker name or description (index 0 or 1).
+    /// The same goes for the playback speed of the section, with `speed=<multiplier>` (ex: `speed=2`).
+    ///
+    /// Every line is checked before returning, so all problems in the file are reported at once.
+    fn from_str(contents: &str) -> Result<Self> {
+        let mut inner = Vec::new();
+        let mut errors = Vec::new();
+        let header_present = contents.lines().next().is_some_and(is_header);
+
+        for (i, line) in contents.lines().enumerate().skip(header_present as usize) {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let parts = line.split(['\t', ',']).collect::<Vec<_>>();
+            let error = |idx: usize, text: &str, kind| CueError {
+                line: i + 1,
+                column: column_offset(&parts, idx) + 1,
+                text: text.to_owned(),
+                kind,
+            };
+
+            if parts.len() <= 5 {
+                errors.push(CueError {
+                    column: line.chars().count() + 1,
+                    ..error(0, line, CueErrorKind::MissingColumn(5))
+                });
+                continue;
+            }
+
+            if !parts[5].contains("Cue Point") {
+                eprintln!(
+                    "[WARN] Skipping marker on line {} because it is not a 'Cue Point'",
+                    i + 1
+                );
+                continue;
+            }
+
+            let [start, end] = [2, 3].map(|idx| {
+                parts[idx]
+                    .parse::<Time>()
+                    .map_err(|e| errors.push(error(idx, parts[idx], CueErrorKind::InvalidTime(e))))
+            });
+            let (Ok(start), Ok(end)) = (start, end) else {
+                continue;
+            };
+
+            if start != end {
+                eprintln!(
+                    "[WARN] Skipping marker on line {} because it has a non-zero duration",
+                    i + 1
+                );
+                continue;
+            }
+
+            let budget = parts[..2].iter().enumerate().find_map(|(idx, part)| {
+                let (raw, budget) = find_budget(part)?;
+                budget
+                    .map_err(|()| errors.push(error(idx, raw, CueErrorKind::InvalidBudget)))
+                    .ok()
+            });
+
+            let speed = parts[..2].iter().enumerate().find_map(|(idx, part)| {
+                let raw = find_tag(part, "speed")?;
+                let speed = raw
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|x| x.is_finite() && *x > 0.0);
+                if speed.is_none() {
+                    errors.push(error(idx, raw, CueErrorKind::InvalidSpeed));
+                }
+                speed
+            });
+
+            let (name, notes) = (strip_tags(parts[0]), strip_tags(parts[1]));
+            inner.push((start, budget, speed, name, notes));
+        }
+
+        if !errors.is_empty() {
+            return Err(Error::Cues { file: None, errors });
+        }
+
+        inner.sort_by_key(|x| x.0);
+        Ok(Self {
+            budgets: inner.iter().map(|x| x.1).collect(),
+            speeds: inner.iter().map(|x| x.2).collect(),
+            names: inner.iter_mut().map(|x| mem::take(&mut x.3)).collect(),
+            notes: inner.iter_mut().map(|x| mem::take(&mut x.4)).collect(),
+            inner: inner.into_iter().map(|x| x.0).collect(),
+        })
+    }
+}
+
+/// Checks if a line is the header row Premiere adds, rather than a marker.
+/// Markers can have any name, so this goes by the time column not being a time (a header is never a cue point either).
+fn is_header(line: &str) -> bool {
+    let parts = line.split(['\t', ',']).collect::<Vec<_>>();
+    parts.get(2).is_some_and(|x| x.parse::<Time>().is_err())
+        && !parts.get(5).is_some_and(|x| x.contains("Cue Point"))
+}
+
+/// Looks for a `budget=` tag in a column, returning the tag's value and the parsed duration.
+/// Budgets are either `M:SS` or a number of seconds.
+fn find_budget(column: &str) -> Option<(&str, result::Result<Duration, ()>)> {
+    let raw = find_tag(column, "budget")?;
+
+    let secs = match raw.split_once(':') {
+        Some((mins, secs)) => mins
+            .parse::<u64>()
+            .ok()
+            .zip(secs.parse::<u64>().ok().filter(|x| *x < 60))
+            .map(|(mins, secs)| (mins * 60 + secs) as f64),
+        None => raw.parse::<f64>().ok(),
+    };
+
+    let budget = secs
+        .filter(|x| x.is_finite() && *x >= 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or(());
+    Some((raw, budget))
+}
+
+/// Looks for a `<name>=<value>` tag in a column, returning the value.
+fn find_tag<'a>(column: &'a str, name: &str) -> Option<&'a str> {
+    column
+        .split_whitespace()
+        .find_map(|x| x.strip_prefix(name)?.strip_prefix('='))
+}
+
+/// Removes the `budget=` and `speed=` tags from a column, leaving the rest of its text.
+fn strip_tags(column: &str) -> String {
+    column
+        .split_whitespace()
+        .filter(|x| !["budget=", "speed="].iter().any(|tag| x.starts_with(tag)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Gets the character offset of a column within its line.
+fn column_offset(parts: &[&str], idx: usize) -> usize {
+    parts[..idx].iter().map(|x| x.chars().count() + 1).sum()
+}
+
 impl Deref for Cues {
     type Target = Vec<Time>;
 
@@ -101,8 +293,13 @@ impl DerefMut for Cues {
 
 #[cfg(test)]
 mod tests {
+    use std::{str::FromStr, time::Duration};
+
     use super::Cues;
-    use crate::time::{time, Time};
+    use crate::{
+        error::{CueErrorKind, Error},
+        time::{time, Time},
+    };
 
     use indoc::indoc;
 
@@ -170,4 +367,124 @@ mod tests {
             assert_eq!(a, b);
         }
     }
+
+    #[test]
+    fn test_parse_named_first_marker() {
+        // Without a header, a name on the first marker doesn't make it one
+        let cues = Cues::from_str("Intro,,00:00:01:00,00:00:01:00,0,Cue Point\n").unwrap();
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues.name(1), Some("Intro"));
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        assert_eq!(Cues::from_str("").unwrap().len(), 0);
+        assert_eq!(Cues::from_str("\n\n").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_parse_budgets() {
+        const CONTENTS: &str = indoc! { r#"
+            Marker Name	Description	In	Out	Duration	Marker Type	
+            	Intro budget=1:30	00:00:04:57	00:00:04:57	00:00:00:00	Flash Cue Point	
+            		00:00:21:31	00:00:21:31	00:00:00:00	Flash Cue Point	
+            budget=45		00:00:30:50	00:00:30:50	00:00:00:00	Flash Cue Point	
+            "#
+        };
+
+        let cues = Cues::from_str(CONTENTS).unwrap();
+        assert!(cues.has_budgets());
+        assert_eq!(cues.budget(0), None);
+        assert_eq!(cues.budget(1), Some(Duration::from_secs(90)));
+        assert_eq!(cues.budget(2), None);
+        assert_eq!(cues.budget(3), Some(Duration::from_secs(45)));
+        assert_eq!(cues.budget(4), None);
+
+        let Err(Error::Cues { errors, .. }) =
+            Cues::from_str(",budget=1:75,00:00:04:57,00:00:04:57,0,Cue Point")
+        else {
+            panic!("expected cue errors");
+        };
+        assert_eq!(errors[0].text, "1:75");
+        assert!(matches!(errors[0].kind, CueErrorKind::InvalidBudget));
+    }
+
+    #[test]
+    fn test_parse_speeds() {
+        const CONTENTS: &str = indoc! { r#"
+            ,Recap speed=2 budget=0:10,00:00:01:00,00:00:01:00,0,Cue Point
+            Live demo,,00:00:02:00,00:00:02:00,0,Cue Point
+            speed=0.5,,00:00:03:00,00:00:03:00,0,Cue Point
+            "#
+        };
+
+        let cues = Cues::from_str(CONTENTS).unwrap();
+        assert_eq!(cues.speed(0), None);
+        assert_eq!(cues.speed(1), Some(2.0));
+        assert_eq!(cues.speed(2), None);
+        assert_eq!(cues.speed(3), Some(0.5));
+        assert_eq!(cues.budget(1), Some(Duration::from_secs(10)));
+
+        // Names are kept without the tags
+        assert_eq!(cues.name(1), None);
+        assert_eq!(cues.name(2), Some("Live demo"));
+        assert_eq!(cues.name(3), None);
+        assert_eq!(cues.notes(1), Some("Recap"));
+        assert_eq!(cues.notes(2), None);
+
+        // A tag in the name of the first marker isn't mistaken for a header
+        let cues = Cues::from_str("speed=0.5,,00:00:01:00,00:00:01:00,0,Cue Point\n").unwrap();
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues.speed(1), Some(0.5));
+
+        let Err(Error::Cues { errors, .. }) =
+            Cues::from_str(",speed=0,00:00:04:57,00:00:04:57,0,Cue Point")
+        else {
+            panic!("expected cue errors");
+        };
+        assert_eq!(errors[0].text, "0");
+        assert!(matches!(errors[0].kind, CueErrorKind::InvalidSpeed));
+    }
+
+    #[test]
+    fn test_parse_missing_column() {
+        const CONTENTS: &str = indoc! { r#"
+            ,,00:00:04:57,00:00:04:57,0,Cue Point
+            ,,00:00:21:31
+            "#
+        };
+
+        let Err(Error::Cues { errors, .. }) = Cues::from_str(CONTENTS) else {
+            panic!("expected cue errors");
+        };
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].column), (2, 14));
+        assert_eq!(errors[0].text, ",,00:00:21:31");
+        assert!(matches!(errors[0].kind, CueErrorKind::MissingColumn(5)));
+    }
+
+    #[test]
+    fn test_parse_collects_all_errors() {
+        const CONTENTS: &str = indoc! { r#"
+            ,,00:00:04:57,00:00:04:57,0,Cue Point
+            ,,00:00:2x:31,00:00:21:31,0,Cue Point
+            ,,00:00:30:50,00:00:30:50,0,Cue Point
+            ,,00:00:38:28,00:00:3a:28,0,Cue Point
+            "#
+        };
+
+        let Err(Error::Cues { errors, .. }) = Cues::from_str(CONTENTS) else {
+            panic!("expected cue errors");
+        };
+
+        let found = errors
+            .iter()
+            .map(|e| (e.line, e.column, e.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(found, [(2, 3, "00:00:2x:31"), (4, 15, "00:00:3a:28")]);
+        assert!(errors
+            .iter()
+            .all(|e| matches!(e.kind, CueErrorKind::InvalidTime(_))));
+    }
 }
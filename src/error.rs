@@ -0,0 +1,195 @@
+use std::{
+    fmt::{self, Display},
+    io,
+    num::ParseIntError,
+    path::PathBuf,
+    result,
+};
+
+use winit::error::OsError;
+
+pub type Result<T> = result::Result<T, Error>;
+
+/// All the ways the presenter can fail.
+#[derive(Debug)]
+pub enum Error {
+    /// One or more lines of the cue file could not be parsed.
+    /// `file` is only known when the cues were loaded with [`crate::cues::Cues::from_file`].
+    Cues {
+        file: Option<PathBuf>,
+        errors: Vec<CueError>,
+    },
+    /// A line of a timing script could not be parsed.
+    Script {
+        line: usize,
+        message: String,
+    },
+    /// A line of a playlist manifest could not be parsed.
+    /// `line` is 0 for problems with the manifest as a whole.
+    Manifest {
+        line: usize,
+        message: String,
+    },
+    /// A project file could not be parsed.
+    Project {
+        file: PathBuf,
+        message: String,
+    },
+    /// Getting or setting an mpv property failed.
+    Property {
+        name: String,
+        error: String,
+    },
+    /// Running an mpv command (seek, pause, show-text, etc.) failed.
+    Command {
+        name: String,
+        error: String,
+    },
+    /// The window could not be created.
+    Window(OsError),
+    Io(io::Error),
+}
+
+/// A single problem found while parsing a cue file.
+/// Line and column are both 1-based, so they can be passed straight to an editor.
+#[derive(Debug)]
+pub struct CueError {
+    pub line: usize,
+    pub column: usize,
+    /// The offending text, the whole line if a column is missing.
+    pub text: String,
+    pub kind: CueErrorKind,
+}
+
+/// The reason a cue line was rejected.
+#[derive(Debug)]
+pub enum CueErrorKind {
+    /// The line has fewer columns than the index that was needed.
+    MissingColumn(usize),
+    /// A timecode column could not be parsed.
+    InvalidTime(TimeError),
+    /// A `budget=` tag is not a valid duration.
+    InvalidBudget,
+    /// A `speed=` tag is not a positive number.
+    InvalidSpeed,
+}
+
+/// The reason a timecode (HH:MM:SS:FF) could not be parsed.
+#[derive(Debug)]
+pub enum TimeError {
+    /// One of the components is not a valid number.
+    Component(ParseIntError),
+    /// There are more than the four HH:MM:SS:FF components.
+    TooManyComponents(usize),
+}
+
+impl Error {
+    /// Wraps an error returned from getting or setting an mpv property.
+    pub fn property(name: impl Into<String>, error: libmpv::Error) -> Self {
+        Self::Property {
+            name: name.into(),
+            error: error.to_string(),
+        }
+    }
+
+    /// Wraps an error returned from an mpv command.
+    pub fn command(name: impl Into<String>, error: libmpv::Error) -> Self {
+        Self::Command {
+            name: name.into(),
+            error: error.to_string(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Cues { file, errors } => {
+                let file = file
+                    .as_ref()
+                    .map(|x| x.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "<cues>".to_owned());
+
+                write!(f, "Found {} problem(s) in cue file `{file}`", errors.len())?;
+                for e in errors {
+                    write!(f, "\n  {file}:{e}")?;
+                }
+                Ok(())
+            }
+            Error::Script { line, message } => {
+                write!(f, "Invalid timing script on line {line}: {message}")
+            }
+            Error::Manifest { line: 0, message } => write!(f, "Invalid playlist: {message}"),
+            Error::Manifest { line, message } => {
+                write!(f, "Invalid playlist on line {line}: {message}")
+            }
+            Error::Project { file, message } => {
+                write!(f, "Invalid project file `{}`: {message}", file.display())
+            }
+            Error::Property { name, error } => write!(f, "mpv property `{name}` failed: {error}"),
+            Error::Command { name, error } => write!(f, "mpv command `{name}` failed: {error}"),
+            Error::Window(e) => write!(f, "Failed to create window: {e}"),
+            Error::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Display for CueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {} (`{}`)",
+            self.line, self.column, self.kind, self.text
+        )
+    }
+}
+
+impl Display for CueErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CueErrorKind::MissingColumn(idx) => write!(f, "missing column {}", idx + 1),
+            CueErrorKind::InvalidTime(e) => e.fmt(f),
+            CueErrorKind::InvalidBudget => {
+                f.write_str("invalid budget (expected `M:SS` or seconds)")
+            }
+            CueErrorKind::InvalidSpeed => f.write_str("invalid speed (expected a positive number)"),
+        }
+    }
+}
+
+impl Display for TimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeError::Component(e) => write!(f, "invalid timecode ({e})"),
+            TimeError::TooManyComponents(n) => {
+                write!(
+                    f,
+                    "invalid timecode (expected at most 4 components, got {n})"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+impl std::error::Error for CueError {}
+impl std::error::Error for CueErrorKind {}
+impl std::error::Error for TimeError {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<OsError> for Error {
+    fn from(e: OsError) -> Self {
+        Self::Window(e)
+    }
+}
+
+impl From<ParseIntError> for TimeError {
+    fn from(e: ParseIntError) -> Self {
+        Self::Component(e)
+    }
+}
@@ -0,0 +1,367 @@
+//! Exporting the frame at every cue, for handouts.
+//! Audiences often ask for "the slides", so the frame each cue stops on can be saved as a PNG, and bundled into a PDF along with the cue's name and notes.
+//! The frames are grabbed by a headless mpv, so this works without a display.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{error::Result, player::mpv::MpvPlayer, playlist::Playlist};
+
+/// Size of a PDF page in points (A4 landscape).
+const PAGE: (f64, f64) = (842.0, 595.0);
+/// Space around the edges of each page.
+const MARGIN: f64 = 36.0;
+/// Height of the space under the image for the name and notes.
+const CAPTION: f64 = 110.0;
+/// Notes are wrapped to lines of at most this many characters.
+const LINE_LENGTH: usize = 125;
+/// Notes longer than this many lines are cut short.
+const MAX_LINES: usize = 5;
+
+/// The frame at a cue, saved as an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Still {
+    pub cue: usize,
+    pub image: PathBuf,
+    /// The name of the cue's marker.
+    pub name: Option<String>,
+    /// The description of the cue's marker.
+    pub notes: Option<String>,
+}
+
+/// Saves the frame at every cue of the playlist (including cue 0, the start) to `dir`, as `cue-<N>.png`.
+/// `fps` overrides the frame rate reported by the videos, like [`crate::presenter::Config::fps`].
+pub fn stills(playlist: &Playlist, fps: Option<f64>, dir: &Path) -> Result<Vec<Still>> {
+    fs::create_dir_all(dir)?;
+    let stills = (0..=playlist.len())
+        .map(|cue| Still {
+            cue,
+            image: dir.join(format!("cue-{cue:03}.png")),
+            name: playlist.name(cue).map(str::to_owned),
+            notes: playlist.notes(cue).map(str::to_owned),
+        })
+        .collect::<Vec<_>>();
+
+    // Each file is only opened once, for all of its cues
+    for (i, segment) in playlist.segments().iter().enumerate() {
+        let frames = stills
+            .iter()
+            .map(|x| (playlist.get(x.cue), x.image.clone()))
+            .filter(|((segment, _), _)| *segment == i)
+            .map(|((_, time), image)| (time, image))
+            .collect::<Vec<_>>();
+        if !frames.is_empty() {
+            MpvPlayer::grab_frames(&segment.media, fps, None, &frames)?;
+        }
+    }
+
+    Ok(stills)
+}
+
+/// Writes a PDF handout with a page for every still, captioned with the cue's name and notes.
+/// The images must be 8 bit RGB PNGs, like the ones saved by [`stills`].
+pub fn pdf(stills: &[Still], path: impl AsRef<Path>) -> Result<()> {
+    fs::write(path, handout(stills)?)?;
+    Ok(())
+}
+
+/// Builds the PDF for [`pdf`].
+fn handout(stills: &[Still]) -> Result<Vec<u8>> {
+    // Objects 1 to 4 are the catalog, page tree and fonts, then each page has a page, contents and image object
+    let kids = (0..stills.len())
+        .map(|i| format!("{} 0 R", 5 + i * 3))
+        .collect::<Vec<_>>();
+    let mut objects = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            stills.len()
+        )
+        .into_bytes(),
+        font("Helvetica"),
+        font("Helvetica-Bold"),
+    ];
+
+    for (i, still) in stills.iter().enumerate() {
+        let id = 5 + i * 3;
+        let png = Png::read(&still.image)?;
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> /XObject << /Im {} 0 R >> >> \
+                 /Contents {} 0 R >>",
+                PAGE.0,
+                PAGE.1,
+                id + 2,
+                id + 1
+            )
+            .into_bytes(),
+        );
+        objects.push(stream("", &contents(still, png.width, png.height)));
+
+        // The PNG's data is already compressed the way PDFs expect, it just needs to be told about the row filters
+        let image = format!(
+            "/Type /XObject /Subtype /Image /Width {0} /Height {1} /ColorSpace /DeviceRGB \
+             /BitsPerComponent 8 /Filter /FlateDecode \
+             /DecodeParms << /Predictor 15 /Colors 3 /BitsPerComponent 8 /Columns {0} >>",
+            png.width, png.height
+        );
+        objects.push(stream(&image, &png.data));
+    }
+
+    let mut out = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend(format!("{} 0 obj\n", i + 1).bytes());
+        out.extend(object);
+        out.extend(b"\nendobj\n");
+    }
+
+    let xref = out.len();
+    out.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).bytes());
+    for offset in offsets {
+        out.extend(format!("{offset:010} 00000 n \n").bytes());
+    }
+    out.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .bytes(),
+    );
+    Ok(out)
+}
+
+/// Draws a page, with the image scaled to fit above the caption.
+fn contents(still: &Still, width: u32, height: u32) -> Vec<u8> {
+    let (area_width, area_height) = (PAGE.0 - MARGIN * 2.0, PAGE.1 - MARGIN * 2.0 - CAPTION);
+    let scale = (area_width / width as f64).min(area_height / height as f64);
+    let (w, h) = (width as f64 * scale, height as f64 * scale);
+    let (x, y) = (
+        (PAGE.0 - w) / 2.0,
+        MARGIN + CAPTION + (area_height - h) / 2.0,
+    );
+    let mut out = format!("q {w:.2} 0 0 {h:.2} {x:.2} {y:.2} cm /Im Do Q\n").into_bytes();
+
+    let title = match &still.name {
+        Some(name) => format!("Cue {}: {name}", still.cue),
+        None => format!("Cue {}", still.cue),
+    };
+    let top = MARGIN + CAPTION - 24.0;
+    out.extend(format!("BT /F2 16 Tf {MARGIN} {top} Td ").bytes());
+    out.extend(text(&title));
+    out.extend(b" Tj ET\n");
+
+    let lines = wrap(still.notes.as_deref().unwrap_or_default());
+    if !lines.is_empty() {
+        out.extend(format!("BT /F1 11 Tf 14 TL {MARGIN} {} Td", top - 24.0).bytes());
+        for line in lines {
+            out.push(b' ');
+            out.extend(text(&line));
+            out.extend(b" Tj T*");
+        }
+        out.extend(b" ET\n");
+    }
+
+    out
+}
+
+/// Wraps notes into lines at word boundaries, cutting them short after [`MAX_LINES`].
+fn wrap(notes: &str) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+    for word in notes.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + word.chars().count() < LINE_LENGTH => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_owned()),
+        }
+    }
+
+    if lines.len() > MAX_LINES {
+        lines.truncate(MAX_LINES);
+        lines[MAX_LINES - 1].push('\u{2026}');
+    }
+    lines
+}
+
+/// Encodes text as a PDF string for the standard fonts, which use the Windows-1252 character set.
+/// Anything outside of it is replaced with `?`.
+fn text(text: &str) -> Vec<u8> {
+    let mut out = vec![b'('];
+    for x in text.chars() {
+        match x {
+            '\\' | '(' | ')' => out.extend([b'\\', x as u8]),
+            '\u{2026}' => out.push(0x85),
+            '\u{2018}' => out.push(0x91),
+            '\u{2019}' => out.push(0x92),
+            '\u{201c}' => out.push(0x93),
+            '\u{201d}' => out.push(0x94),
+            '\u{2013}' => out.push(0x96),
+            '\u{2014}' => out.push(0x97),
+            x if x == ' ' || x.is_ascii_graphic() || ('\u{a0}'..='\u{ff}').contains(&x) => {
+                out.push(x as u8)
+            }
+            _ => out.push(b'?'),
+        }
+    }
+    out.push(b')');
+    out
+}
+
+/// A standard font, which every PDF reader has so it doesn't need embedding.
+fn font(name: &str) -> Vec<u8> {
+    format!("<< /Type /Font /Subtype /Type1 /BaseFont /{name} /Encoding /WinAnsiEncoding >>")
+        .into_bytes()
+}
+
+/// A stream object, with the entries of `dict` added to its dictionary.
+fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+    let mut out = format!("<< {dict} /Length {} >>\nstream\n", data.len()).into_bytes();
+    out.extend(data);
+    out.extend(b"\nendstream");
+    out
+}
+
+/// The size and compressed pixel data of a PNG.
+struct Png {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl Png {
+    /// Reads a PNG, which must be 8 bit RGB without interlacing so its data can be copied straight into a PDF.
+    fn read(path: &Path) -> Result<Self> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("`{}` is not an 8 bit RGB PNG", path.display()),
+            )
+        };
+
+        let bytes = fs::read(path)?;
+        let mut chunks = bytes
+            .strip_prefix(b"\x89PNG\r\n\x1a\n")
+            .ok_or_else(invalid)?;
+        let (mut header, mut data) = (None, Vec::new());
+        while chunks.len() >= 12 {
+            let len = u32::from_be_bytes([chunks[0], chunks[1], chunks[2], chunks[3]]) as usize;
+            let (kind, rest) = chunks[4..].split_at(4);
+            let body = rest.get(..len).ok_or_else(invalid)?;
+            match kind {
+                b"IHDR" => header = Some(body),
+                b"IDAT" => data.extend(body),
+                b"IEND" => break,
+                _ => {}
+            }
+            // Skipping the CRC after the body
+            chunks = rest.get(len + 4..).ok_or_else(invalid)?;
+        }
+
+        // Bit depth, colour type, compression, filter and interlace method
+        match header {
+            Some(header) if header.len() == 13 && header[8..] == [8, 2, 0, 0, 0] => Ok(Self {
+                width: u32::from_be_bytes([header[0], header[1], header[2], header[3]]),
+                height: u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+                data,
+            }),
+            _ => Err(invalid().into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, str};
+
+    use super::{handout, wrap, Still, LINE_LENGTH, MAX_LINES};
+
+    /// Writes a PNG with the given header, and some stand-in pixel data.
+    fn png(path: &Path, width: u32, height: u32, colour: u8) {
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut header = width.to_be_bytes().to_vec();
+        header.extend(height.to_be_bytes());
+        header.extend([8, colour, 0, 0, 0]);
+
+        for (kind, body) in [
+            (b"IHDR", header),
+            (b"IDAT", b"pixels".to_vec()),
+            (b"IEND", Vec::new()),
+        ] {
+            bytes.extend((body.len() as u32).to_be_bytes());
+            bytes.extend(kind);
+            bytes.extend(body);
+            bytes.extend([0; 4]);
+        }
+        fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_handout() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        png(&dir.join("cue-000.png"), 1920, 1080, 2);
+        png(&dir.join("cue-001.png"), 1920, 1080, 2);
+        png(&dir.join("alpha.png"), 1920, 1080, 6);
+
+        let mut stills = vec![
+            Still {
+                cue: 0,
+                image: dir.join("cue-000.png"),
+                name: None,
+                notes: None,
+            },
+            Still {
+                cue: 1,
+                image: dir.join("cue-001.png"),
+                name: Some("Intro (part 1)".into()),
+                notes: Some("Don\u{2019}t rush".into()),
+            },
+        ];
+        let pdf = handout(&stills).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("(Cue 0) Tj"));
+        assert!(text.contains("(Cue 1: Intro \\(part 1\\)) Tj"));
+        assert!(pdf.windows(12).any(|x| x == b"(Don\x92t rush)"));
+        assert!(text.contains("/Length 6 >>\nstream\npixels\nendstream"));
+
+        // Every object is where the cross reference table says it is
+        let xref = pdf.windows(6).position(|x| x == b"\nxref\n").unwrap() + 1;
+        let table = str::from_utf8(&pdf[xref..]).unwrap();
+        let offsets = table
+            .lines()
+            .skip(3)
+            .take_while(|x| x.ends_with(" n "))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets.len(), 4 + 2 * 3);
+        for (i, offset) in offsets.iter().enumerate() {
+            let offset = offset[..10].parse::<usize>().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", i + 1).as_bytes()));
+        }
+        assert!(table.ends_with(&format!("startxref\n{xref}\n%%EOF\n")));
+
+        // Images with transparency can't be copied across
+        stills[0].image = dir.join("alpha.png");
+        assert!(handout(&stills).is_err());
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert!(wrap("").is_empty());
+        assert_eq!(wrap("  one   two "), ["one two"]);
+
+        let long = "word ".repeat(1000);
+        let lines = wrap(&long);
+        assert_eq!(lines.len(), MAX_LINES);
+        assert!(lines.iter().all(|x| x.chars().count() <= LINE_LENGTH));
+        assert!(lines[MAX_LINES - 1].ends_with('\u{2026}'));
+    }
+}
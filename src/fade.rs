@@ -0,0 +1,119 @@
+//! Audio fades.
+//! Pausing at a cue cuts the audio off mid-sound, which clicks or pops through the speakers, and resuming slams it back on.
+//! Instead the volume is faded out just before each cue and faded back in when playback resumes.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel::RecvTimeoutError;
+
+use crate::{player::Player, presenter::Presenter, session::Event, state::State};
+
+/// How often the volume is updated while fading.
+const TICK: Duration = Duration::from_millis(20);
+
+/// Gets the volume as a fraction of the full volume, while playing.
+/// `since_start` is how long playback has been going, and `until_cue` how long until it stops at the next cue (in real seconds, not video time).
+pub fn gain(fade: Duration, since_start: f64, until_cue: f64) -> f64 {
+    let fade = fade.as_secs_f64();
+    if fade <= 0.0 {
+        return 1.0;
+    }
+
+    (since_start.min(until_cue) / fade).clamp(0.0, 1.0)
+}
+
+/// Starts fading the volume on a background thread.
+/// The volume the player has when this is called is taken as the full volume.
+pub fn start<P: Player + 'static>(presenter: &Presenter<P>, fade: Duration) {
+    let presenter = presenter.clone();
+    thread::spawn(move || {
+        let full = match presenter.player().volume() {
+            Ok(x) => x,
+            Err(e) => {
+                presenter.log(Event::Error {
+                    message: format!("Audio fades are disabled: {e}"),
+                });
+                return;
+            }
+        };
+
+        let statuses = presenter.watch();
+        let Ok(mut status) = statuses.recv() else {
+            return;
+        };
+
+        let mut playing_since = None::<Instant>;
+        let mut last = full;
+        loop {
+            // The volume is left where it is while stopped, so it stays faded out until playback resumes.
+            // Nothing needs doing until the state changes again.
+            let State::Playing { target } = status.state else {
+                playing_since = None;
+                match statuses.recv() {
+                    Ok(x) => status = x,
+                    Err(_) => break,
+                }
+                continue;
+            };
+            let since = playing_since.get_or_insert_with(Instant::now).elapsed();
+
+            // Only fade out for a cue in the same file, playing into the next one doesn't stop.
+            // The time until the cue is in video time, so it is scaled by the speed to get how long it really takes.
+            let (segment, time) = presenter.playlist().get(target);
+            let until = match segment == status.segment && !time.is_end() {
+                true => presenter
+                    .player()
+                    .position()
+                    .ok()
+                    .map(|x| (time.as_secs(status.fps) - x) / status.speed),
+                false => Some(f64::INFINITY),
+            };
+
+            let volume = until.map(|x| full * gain(fade, since.as_secs_f64(), x));
+            if let Some(volume) = volume.filter(|x| (x - last).abs() >= 0.5) {
+                last = volume;
+                if let Err(e) = presenter.player().set_volume(volume) {
+                    presenter.log(Event::Error {
+                        message: e.to_string(),
+                    });
+                }
+            }
+
+            match statuses.recv_timeout(TICK) {
+                Ok(x) => status = x,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::gain;
+
+    #[test]
+    fn test_gain() {
+        let fade = Duration::from_millis(500);
+
+        // Fading in after resuming
+        assert_eq!(gain(fade, 0.0, 10.0), 0.0);
+        assert_eq!(gain(fade, 0.25, 10.0), 0.5);
+        assert_eq!(gain(fade, 2.0, 10.0), 1.0);
+
+        // Fading out before the cue, reaching silence right on it
+        assert_eq!(gain(fade, 2.0, 0.25), 0.5);
+        assert_eq!(gain(fade, 2.0, 0.0), 0.0);
+        assert_eq!(gain(fade, 2.0, -0.1), 0.0);
+
+        // A section shorter than two fades never reaches full volume
+        assert_eq!(gain(fade, 0.3, 0.2), 0.4);
+
+        assert_eq!(gain(Duration::ZERO, 0.0, 0.0), 1.0);
+    }
+}
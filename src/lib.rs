@@ -0,0 +1,26 @@
+#![feature(decl_macro)]
+
+//! The cue engine behind video-presenter.
+//! Use [`presenter::Presenter`] to play a video that pauses at each cue, or [`cues::Cues`] and [`time::Time`] on their own to work with marker files.
+
+pub mod cues;
+pub mod error;
+pub mod export;
+pub mod fade;
+pub mod music;
+pub mod overview;
+pub mod pace;
+pub mod player;
+pub mod playlist;
+pub mod presenter;
+pub mod project;
+pub mod rehearsal;
+pub mod reload;
+pub mod resume;
+pub mod script;
+pub mod session;
+pub mod state;
+pub mod thumbnails;
+pub mod time;
+pub mod timeline;
+pub mod transition;
@@ -1,91 +1,354 @@
-#![feature(decl_macro)]
-
-use std::{
-    sync::{atomic::Ordering, Arc},
-    thread,
-};
+use std::{io, path::PathBuf, thread, time::Duration};
 
 use anyhow::Result;
+use clap::Parser;
 use winit::{
-    event::VirtualKeyCode,
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    event::{Event, VirtualKeyCode},
+    event_loop::{ControlFlow, EventLoopBuilder},
+    window::{Fullscreen, WindowBuilder},
 };
 use winit_input_helper::WinitInputHelper;
 
-mod app;
+use video_presenter::{
+    error::Error,
+    export, fade,
+    music::Music,
+    overview::{self, Overview},
+    pace::Pace,
+    player::mpv::MpvPlayer,
+    presenter::Presenter,
+    project::Project,
+    rehearsal::{Recording, Rehearsal},
+    reload,
+    resume::Resume,
+    script::Script,
+    state::Command,
+    thumbnails, timeline,
+};
+
 mod args;
-mod cues;
-mod time;
-use app::App;
+mod control;
+use args::{Args, ExportArgs, Subcommands};
 
 fn main() -> Result<()> {
-    // Create window
+    // Parse command line arguments with clap
+    let mut args = Args::parse();
+    if let Some(Subcommands::ExportStills(export)) = args.command.take() {
+        return export_stills(export);
+    }
+    if args.list_audio_devices {
+        return list_audio_devices();
+    }
+
+    let project = args.project.as_ref().map(Project::from_file).transpose()?;
+    let script = args.script.as_ref().map(Script::from_file).transpose()?;
+    let targets = args.targets.as_ref().map(Script::from_file).transpose()?;
+    let mut rehearse = args.rehearse.clone();
+    let auto_resume = args.resume;
+    let fade = args
+        .fade
+        .or(project.as_ref().and_then(|x| x.fade))
+        .filter(|x| *x > 0.0)
+        .map(Duration::from_secs_f64);
+    let music = args.music(project.as_ref());
+
+    // Without a window, the presentation is driven from stdin (or the script)
+    if args.headless {
+        let app = Presenter::new(args.into_config(project.as_ref(), None))?;
+        let recording = rehearse.is_some().then(|| Rehearsal::start(&app, targets));
+
+        // There is nobody to ask, so only resume if told to
+        if let Some(resume) = Resume::load(app.config()).filter(|_| auto_resume) {
+            app.goto(resume.cue);
+        }
+        Resume::track(&app);
+
+        match script {
+            Some(script) => script.run(&app),
+            None => control::run(&app, io::stdin().lock())?,
+        }
+
+        save_rehearsal(rehearse, recording);
+        return Ok(());
+    }
+
+    let keys = keymap(project.as_ref())?;
+
+    // Create window, fullscreen on the project's monitor if it has one
     let mut input = WinitInputHelper::new();
-    let event_loop = EventLoop::new();
+    // User events are sent when more thumbnails are ready for the overview
+    let event_loop = EventLoopBuilder::with_user_event().build();
+    let proxy = event_loop.create_proxy();
+    let monitor = project.as_ref().and_then(|x| x.monitor);
+    let fullscreen = monitor.and_then(|i| {
+        let monitor = event_loop.available_monitors().nth(i);
+        if monitor.is_none() {
+            eprintln!("[WARN] There is no monitor #{i}, opening a window instead");
+        }
+        Some(Fullscreen::Borderless(Some(monitor?)))
+    });
     let window = WindowBuilder::new()
         .with_title("video-presenter")
+        .with_fullscreen(fullscreen)
         .build(&event_loop)
-        .unwrap();
+        .map_err(Error::Window)?;
     // Get window handle.
     // Its used for telling mpv where to render.
     let wid = u64::from(window.id());
 
-    // Create the app instance, this inits mpv
-    let app = Arc::new(App::new(wid)?);
+    // Create the app instance, this inits mpv and starts the presentation thread
+    let app = Presenter::new(args.into_config(project.as_ref(), Some(wid)))?;
     window.set_title(&format!("video-presenter \u{2013} {}", app.video_name()));
 
-    // Start the mpv event loop
-    let app2 = app.clone();
-    thread::spawn(move || app2.event_loop());
+    let mut recording = rehearse.is_some().then(|| Rehearsal::start(&app, targets));
+
+    // Offer to jump back to where the last session was left off
+    let mut resume = Resume::load(app.config());
+    if let Some(cue) = resume.as_ref().map(|x| x.cue) {
+        if auto_resume {
+            resume = None;
+            app.goto(cue);
+        } else {
+            println!("[*] Last session was left at cue #{cue}, press R to resume");
+            app.info(format!("Press R to resume at cue #{cue}"));
+        }
+    }
+    Resume::track(&app);
+
+    // Pick up changes to the cue file while presenting
+    reload::watch(&app);
+
+    // Warn on screen when a section runs over its budget
+    if app.playlist().has_budgets() {
+        Pace::start(&app);
+    }
+
+    // Draw the timeline whenever it is toggled on
+    timeline::start(&app);
+
+    let mut overview = Overview::new(&app);
+    let mut thumbnails_started = false;
+
+    // Smooth out the audio stopping and starting at each cue
+    if let (Some(fade), true) = (fade, app.config().audio) {
+        fade::start(&app, fade);
+    }
+
+    // Keep the music bed going through pauses, ducking it under the video
+    if let Some(music) = music {
+        Music::new(music, app.config().audio_device.as_deref())?.start(&app);
+    }
+
+    // Play through the timing script in the background, keys still work as normal
+    if let Some(script) = script {
+        let app = app.clone();
+        thread::spawn(move || script.run(&app));
+    }
 
     // Start the winit event loop
     event_loop.run(move |event, _window, control_flow| {
+        if let Event::UserEvent(()) = event {
+            overview.refresh();
+        }
+
         if input.update(&event) {
             if input.close_requested() || input.destroyed() {
+                save_rehearsal(rehearse.take(), recording.take());
                 *control_flow = ControlFlow::Exit;
             }
 
-            if input.key_pressed(VirtualKeyCode::P) {
-                let paused = app.mpv.get_property::<bool>("pause").unwrap();
-                app.mpv.set_property("pause", !paused).unwrap();
+            // The overview takes all the keys while it is open
+            if overview.is_open() {
+                handle_overview(&mut overview, &input);
+                return;
             }
 
-            if input.key_pressed(VirtualKeyCode::Space) {
-                let paused = app.mpv.get_property::<bool>("pause").unwrap();
+            if input.key_pressed(VirtualKeyCode::R) {
+                if let Some(resume) = resume.take() {
+                    app.goto(resume.cue);
+                }
+            }
 
-                if paused {
-                    app.mpv.unpause().unwrap();
-                } else {
-                    app.seek_f().unwrap();
+            if input.key_pressed(VirtualKeyCode::O) {
+                // Making the thumbnails competes with the talk for the CPU and disk, so it waits until the overview is wanted.
+                // They are cached, so this is only slow the first time for each video.
+                if !thumbnails_started {
+                    thumbnails_started = true;
+                    let proxy = proxy.clone();
+                    thumbnails::start(&app, move || {
+                        let _ = proxy.send_event(());
+                    });
                 }
+                overview.open();
+                return;
             }
 
-            if input.key_pressed(VirtualKeyCode::Right) {
-                app.mpv.pause().unwrap();
-                app.seek_f().unwrap();
+            handle_input(&app, &input, &keys);
+        }
+    });
+}
 
-                let cue = app.current_cue.load(Ordering::Relaxed);
-                app.info(format!("#{cue}"));
-            }
+/// Prints the audio outputs mpv can use.
+fn list_audio_devices() -> Result<()> {
+    let devices = MpvPlayer::audio_devices()?;
+    let width = devices
+        .iter()
+        .map(|x| x.name.len())
+        .max()
+        .unwrap_or_default();
 
-            if input.key_pressed(VirtualKeyCode::Left) {
-                app.mpv.pause().unwrap();
-                app.seek_r().unwrap();
+    println!("[*] Audio devices (pass the name to --audio-device)");
+    for device in devices {
+        println!("  {:width$}  {}", device.name, device.description);
+    }
+    Ok(())
+}
 
-                let cue = app.current_cue.load(Ordering::Relaxed);
-                app.info(format!("#{cue}"));
-            }
+/// Saves the frame at every cue, and the PDF handout if asked for.
+fn export_stills(args: ExportArgs) -> Result<()> {
+    let project = args.project.as_ref().map(Project::from_file).transpose()?;
+    let config = args.config(project.as_ref());
+    let playlist = config.load_playlist()?;
 
-            if input.key_pressed(VirtualKeyCode::Period) {
-                app.mpv.seek_frame().unwrap();
-                app.auto_cue();
-            }
+    let stills = export::stills(&playlist, config.fps, &args.out)?;
+    println!(
+        "[*] Saved {} stills to `{}`",
+        stills.len(),
+        args.out.display()
+    );
 
-            if input.key_pressed(VirtualKeyCode::Comma) {
-                app.mpv.seek_frame_backward().unwrap();
-                app.auto_cue();
-            }
+    if let Some(path) = args.pdf {
+        export::pdf(&stills, &path)?;
+        println!("[*] Saved handout to `{}`", path.display());
+    }
+    Ok(())
+}
+
+/// Writes the rehearsal report, if one was being recorded.
+fn save_rehearsal(path: Option<PathBuf>, recording: Option<Recording>) {
+    let (Some(path), Some(recording)) = (path, recording) else {
+        return;
+    };
+
+    let report = recording.report();
+    match report.save(&path) {
+        Ok(()) => println!("[*] Saved rehearsal report to `{}`", path.display()),
+        Err(e) => eprintln!("[-] Failed to save rehearsal report: {e}"),
+    }
+}
+
+/// Sends the command for any key that was pressed this frame.
+fn handle_input(app: &Presenter, input: &WinitInputHelper, keys: &[(VirtualKeyCode, Command)]) {
+    for (key, cmd) in keys {
+        if input.key_pressed(*key) {
+            app.send(cmd.clone());
         }
-    });
+    }
+}
+
+/// Moves around the overview, jumping to the selected cue with enter or space.
+fn handle_overview(overview: &mut Overview, input: &WinitInputHelper) {
+    const COLUMNS: isize = overview::COLUMNS as isize;
+    const PAGE: isize = overview::PAGE as isize;
+
+    #[rustfmt::skip]
+    let moves = [
+        (VirtualKeyCode::Left,     -1),
+        (VirtualKeyCode::Right,    1),
+        (VirtualKeyCode::Up,       -COLUMNS),
+        (VirtualKeyCode::Down,     COLUMNS),
+        (VirtualKeyCode::PageUp,   -PAGE),
+        (VirtualKeyCode::PageDown, PAGE),
+    ];
+    for (key, delta) in moves {
+        if input.key_pressed(key) {
+            overview.select(delta);
+        }
+    }
+
+    if input.key_pressed(VirtualKeyCode::Return) || input.key_pressed(VirtualKeyCode::Space) {
+        overview.go();
+    } else if input.key_pressed(VirtualKeyCode::Escape) || input.key_pressed(VirtualKeyCode::O) {
+        overview.close();
+    }
+}
+
+/// Keys that are handled before the key bindings, so can't be rebound, and what they do.
+const RESERVED_KEYS: [(VirtualKeyCode, &str); 2] = [
+    (VirtualKeyCode::R, "resuming"),
+    (VirtualKeyCode::O, "the overview"),
+];
+
+/// Gets the key bindings, the defaults with any from the project replacing them.
+/// Binding one of the [`RESERVED_KEYS`] is an error, rather than the binding never working.
+fn keymap(project: Option<&Project>) -> Result<Vec<(VirtualKeyCode, Command)>> {
+    #[rustfmt::skip]
+    let mut keys = vec![
+        (VirtualKeyCode::P,        Command::TogglePause),
+        (VirtualKeyCode::Space,    Command::Advance),
+        (VirtualKeyCode::Right,    Command::Next),
+        (VirtualKeyCode::Left,     Command::Back),
+        (VirtualKeyCode::Period,   Command::FrameStep),
+        (VirtualKeyCode::Comma,    Command::FrameBackStep),
+        (VirtualKeyCode::B,        Command::Blackout),
+        (VirtualKeyCode::RBracket, Command::Faster),
+        (VirtualKeyCode::LBracket, Command::Slower),
+        (VirtualKeyCode::Back,     Command::NormalSpeed),
+        (VirtualKeyCode::T,        Command::Timeline),
+    ];
+
+    for (name, cmd) in project.map(|x| x.keys.as_slice()).unwrap_or_default() {
+        let key = parse_key(name).ok_or_else(|| anyhow::anyhow!("Unknown key `{name}`"))?;
+        if let Some((_, action)) = RESERVED_KEYS.iter().find(|x| x.0 == key) {
+            anyhow::bail!("Key `{name}` can't be rebound, it is used for {action}");
+        }
+        keys.retain(|x| x.0 != key);
+        keys.push((key, cmd.clone()));
+    }
+
+    Ok(keys)
+}
+
+/// Gets a key from its name, like `Space`, `PageDown`, `F5` or `A`.
+fn parse_key(name: &str) -> Option<VirtualKeyCode> {
+    use VirtualKeyCode as K;
+
+    #[rustfmt::skip]
+    const LETTERS: [K; 26] = [
+        K::A, K::B, K::C, K::D, K::E, K::F, K::G, K::H, K::I, K::J, K::K, K::L, K::M,
+        K::N, K::O, K::P, K::Q, K::R, K::S, K::T, K::U, K::V, K::W, K::X, K::Y, K::Z,
+    ];
+    #[rustfmt::skip]
+    const DIGITS: [K; 10] = [
+        K::Key0, K::Key1, K::Key2, K::Key3, K::Key4, K::Key5, K::Key6, K::Key7, K::Key8, K::Key9,
+    ];
+    #[rustfmt::skip]
+    const FUNCTION: [K; 12] = [
+        K::F1, K::F2, K::F3, K::F4, K::F5, K::F6, K::F7, K::F8, K::F9, K::F10, K::F11, K::F12,
+    ];
+
+    let index = |x: &str, base: u8| Some(usize::from(x.as_bytes().first()?.checked_sub(base)?));
+    Some(match name {
+        x if x.len() == 1 && x.chars().all(|x| x.is_ascii_uppercase()) => LETTERS[index(x, b'A')?],
+        x if x.len() == 1 && x.chars().all(|x| x.is_ascii_digit()) => DIGITS[index(x, b'0')?],
+        x if x.starts_with('F') => *FUNCTION.get(x[1..].parse::<usize>().ok()?.checked_sub(1)?)?,
+        "Space" => K::Space,
+        "Enter" | "Return" => K::Return,
+        "Escape" => K::Escape,
+        "Tab" => K::Tab,
+        "Backspace" => K::Back,
+        "Left" => K::Left,
+        "Right" => K::Right,
+        "Up" => K::Up,
+        "Down" => K::Down,
+        "PageUp" => K::PageUp,
+        "PageDown" => K::PageDown,
+        "Home" => K::Home,
+        "End" => K::End,
+        "Period" => K::Period,
+        "LBracket" => K::LBracket,
+        "RBracket" => K::RBracket,
+        "Comma" => K::Comma,
+        _ => return None,
+    })
 }
@@ -0,0 +1,130 @@
+//! Background music.
+//! A music bed plays from its own mpv instance, so it keeps going while the video is held at a cue instead of leaving the room in silence.
+//! While the video is playing (with audio), the music is ducked under it.
+
+use std::{path::PathBuf, thread, time::Duration};
+
+use crossbeam::channel::RecvTimeoutError;
+use libmpv::{FileState, Mpv};
+use serde::Deserialize;
+
+use crate::{
+    error::{Error, Result},
+    player::Player,
+    presenter::Presenter,
+    session::Event,
+    state::State,
+};
+
+/// How often the volume is updated while ducking.
+const TICK: Duration = Duration::from_millis(20);
+/// How long it takes to duck or un-duck the music.
+const RAMP: Duration = Duration::from_secs(1);
+
+/// Settings for the music bed.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MusicConfig {
+    /// The audio file to loop.
+    pub file: PathBuf,
+    /// Volume of the music while the video is stopped, as a percentage.
+    #[serde(default = "MusicConfig::default_volume")]
+    pub volume: f64,
+    /// Volume of the music while the video is playing.
+    #[serde(default = "MusicConfig::default_duck")]
+    pub duck: f64,
+}
+
+impl MusicConfig {
+    pub const fn default_volume() -> f64 {
+        50.0
+    }
+
+    pub const fn default_duck() -> f64 {
+        15.0
+    }
+}
+
+/// A looping music bed.
+pub struct Music {
+    mpv: Mpv,
+    config: MusicConfig,
+}
+
+impl Music {
+    /// Creates an audio only mpv instance and starts playing the music on a loop.
+    /// The music goes to the same audio output as the video, if one was chosen.
+    pub fn new(config: MusicConfig, device: Option<&str>) -> Result<Self> {
+        let mpv = Mpv::new().map_err(|e| Error::command("create", e))?;
+        let device = ("audio-device", device.unwrap_or("auto"));
+        for (name, value) in [("video", "no"), ("loop-file", "inf"), ("osc", "no"), device] {
+            mpv.set_property(name, value)
+                .map_err(|e| Error::property(name, e))?;
+        }
+        mpv.set_property("volume", config.volume)
+            .map_err(|e| Error::property("volume", e))?;
+
+        let file = config.file.to_string_lossy();
+        mpv.playlist_load_files(&[(file.as_ref(), FileState::Replace, None)])
+            .map_err(|e| Error::command("loadfile", e))?;
+        Ok(Self { mpv, config })
+    }
+
+    /// Ducks the music on a background thread whenever the presentation is playing.
+    /// If the video's audio is off, there is nothing to duck under so the music stays at its full volume.
+    pub fn start<P: Player + 'static>(self, presenter: &Presenter<P>) {
+        let statuses = presenter.watch();
+        let duck = presenter.config().audio;
+        let presenter = presenter.clone();
+
+        thread::spawn(move || {
+            let (full, ducked) = (self.config.volume, self.config.duck);
+            let step = (full - ducked).abs() * TICK.as_secs_f64() / RAMP.as_secs_f64();
+
+            let mut playing = false;
+            let mut volume = full;
+            loop {
+                match statuses.recv_timeout(TICK) {
+                    Ok(status) => playing = matches!(status.state, State::Playing { .. }),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+
+                let target = if playing && duck { ducked } else { full };
+                let next = ramp(volume, target, step);
+                if next == volume {
+                    continue;
+                }
+
+                volume = next;
+                if let Err(e) = self.mpv.set_property("volume", volume) {
+                    presenter.log(Event::Error {
+                        message: Error::property("volume", e).to_string(),
+                    });
+                }
+            }
+        });
+    }
+}
+
+/// Moves a volume towards the target, by at most `step`.
+pub fn ramp(volume: f64, target: f64, step: f64) -> f64 {
+    match volume < target {
+        true => (volume + step).min(target),
+        false => (volume - step).max(target),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ramp;
+
+    #[test]
+    fn test_ramp() {
+        assert_eq!(ramp(50.0, 15.0, 10.0), 40.0);
+        assert_eq!(ramp(20.0, 15.0, 10.0), 15.0);
+        assert_eq!(ramp(15.0, 50.0, 10.0), 25.0);
+        assert_eq!(ramp(45.0, 50.0, 10.0), 50.0);
+        assert_eq!(ramp(50.0, 50.0, 10.0), 50.0);
+    }
+}
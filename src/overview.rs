@@ -0,0 +1,288 @@
+//! The overview.
+//! Like a slide sorter, this replaces the video with a grid of thumbnails, one for every cue, so a cue can be found by sight and jumped straight to.
+//! The thumbnails come from the [cache](crate::thumbnails), any that haven't been made yet are left blank.
+
+use std::{cmp, path::PathBuf};
+
+use crate::{
+    error::Result,
+    player::{mpv::MpvPlayer, Player, Tile, OVERLAY_HEIGHT, OVERLAY_WIDTH},
+    presenter::Presenter,
+    session::Event,
+    thumbnails,
+    timeline::{rect, text},
+};
+
+/// Id of the overview's overlay, drawn over the [timeline](crate::timeline::OVERLAY_ID).
+pub const OVERLAY_ID: u32 = 2;
+/// Number of thumbnails in each row of the grid.
+pub const COLUMNS: usize = 5;
+/// Number of rows on each page of the grid.
+pub const ROWS: usize = 4;
+/// Number of thumbnails on each page.
+pub const PAGE: usize = COLUMNS * ROWS;
+
+/// Space between the grid and the sides of the screen.
+const MARGIN: u32 = 60;
+/// Space between the thumbnails.
+const GAP: u32 = 30;
+/// Space under each thumbnail for its label.
+const LABEL: u32 = 44;
+/// Thickness of the outline around the selected thumbnail.
+const OUTLINE: u32 = 6;
+/// Labels longer than this are cut short, so they don't run into the next one.
+const MAX_LABEL: usize = 18;
+
+/// A grid of every cue to pick from.
+pub struct Overview<P: Player = MpvPlayer> {
+    presenter: Presenter<P>,
+    thumbnails: Vec<PathBuf>,
+    labels: Vec<String>,
+    /// The selected cue, or `None` when closed.
+    selected: Option<usize>,
+}
+
+impl<P: Player> Overview<P> {
+    /// Creates a closed overview of a presentation.
+    pub fn new(presenter: &Presenter<P>) -> Self {
+        Self {
+            presenter: presenter.clone(),
+            thumbnails: Vec::new(),
+            labels: Vec::new(),
+            selected: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.selected.is_some()
+    }
+
+    /// The selected cue, if open.
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Shows the grid, with the current cue selected.
+    /// The cues are read again every time, so it is always up to date after a reload.
+    pub fn open(&mut self) {
+        let playlist = self.presenter.playlist();
+        self.thumbnails = match thumbnails::paths(&playlist) {
+            Ok(x) => x,
+            Err(e) => return self.report(Err(e)),
+        };
+        self.labels = (0..=playlist.len())
+            .map(|cue| match playlist.name(cue) {
+                Some(name) => format!("#{cue} \u{2013} {name}"),
+                None => format!("#{cue}"),
+            })
+            .collect();
+
+        self.selected = Some(cmp::min(self.presenter.current_cue(), playlist.len()));
+        self.draw();
+    }
+
+    /// Hides the grid, showing the video again.
+    pub fn close(&mut self) {
+        self.selected = None;
+        let player = self.presenter.player();
+        let res = player.set_tiles(None);
+        self.report(res.and(player.set_overlay(OVERLAY_ID, None)));
+    }
+
+    /// Moves the selection by some number of cues, stopping at the first and last.
+    pub fn select(&mut self, delta: isize) {
+        let Some(selected) = self.selected else {
+            return;
+        };
+
+        let last = self.thumbnails.len().saturating_sub(1);
+        self.selected = Some(selected.saturating_add_signed(delta).min(last));
+        self.draw();
+    }
+
+    /// Draws the grid again if it is open, to show any thumbnails that have been made since.
+    pub fn refresh(&self) {
+        self.draw();
+    }
+
+    /// Jumps to the selected cue and closes the grid.
+    pub fn go(&mut self) {
+        if let Some(cue) = self.selected {
+            self.close();
+            self.presenter.goto(cue);
+        }
+    }
+
+    /// Shows the page of the grid with the selected cue on it.
+    fn draw(&self) {
+        let Some(selected) = self.selected else {
+            return;
+        };
+
+        let page = selected / PAGE * PAGE;
+        let cues = page..cmp::min(page + PAGE, self.thumbnails.len());
+        let tiles = cues
+            .clone()
+            .filter(|cue| self.thumbnails[*cue].exists())
+            .map(|cue| tile(cue - page, self.thumbnails[cue].clone()))
+            .collect::<Vec<_>>();
+
+        let mut ass = String::new();
+        for cue in cues {
+            let Tile {
+                x,
+                y,
+                width,
+                height,
+                ..
+            } = tile(cue - page, PathBuf::new());
+            let mut label = self.labels[cue].chars().take(MAX_LABEL).collect::<String>();
+            if self.labels[cue].chars().count() > MAX_LABEL {
+                label.push('\u{2026}');
+            }
+            ass += &text(7, x, y + height + 4, &label);
+
+            if cue == selected {
+                let (x, y) = (x - OUTLINE, y - OUTLINE);
+                let (width, height) = (width + OUTLINE * 2, height + OUTLINE * 2);
+                for (x, y, width, height) in [
+                    (x, y, width, OUTLINE),
+                    (x, y + height - OUTLINE, width, OUTLINE),
+                    (x, y, OUTLINE, height),
+                    (x + width - OUTLINE, y, OUTLINE, height),
+                ] {
+                    ass += &rect(x, y, width, height, "00D7FF", "00");
+                }
+            }
+        }
+
+        let pages = self.thumbnails.len().div_ceil(PAGE);
+        if pages > 1 {
+            let page = format!("{}/{pages}", page / PAGE + 1);
+            ass += &text(9, OVERLAY_WIDTH - MARGIN, MARGIN / 4, &page);
+        }
+
+        let player = self.presenter.player();
+        let res = player.set_tiles(Some(&tiles));
+        self.report(res.and(player.set_overlay(OVERLAY_ID, Some(&ass))));
+    }
+
+    /// Logs an error and shows it on screen.
+    fn report(&self, res: Result<()>) {
+        if let Err(e) = res {
+            self.presenter.log(Event::Error {
+                message: e.to_string(),
+            });
+            self.presenter.info(format!("Error: {e}"));
+        }
+    }
+}
+
+/// Gets the position of a thumbnail in the grid, from its index on the page.
+fn tile(index: usize, image: PathBuf) -> Tile {
+    let (columns, rows) = (COLUMNS as u32, ROWS as u32);
+    let width = (OVERLAY_WIDTH - MARGIN * 2 - GAP * (columns - 1)) / columns;
+    let height = width * 9 / 16;
+    let top = (OVERLAY_HEIGHT - (height + LABEL) * rows) / 2;
+
+    let (column, row) = ((index % COLUMNS) as u32, (index / COLUMNS) as u32);
+    Tile {
+        image,
+        x: MARGIN + (width + GAP) * column,
+        y: top + (height + LABEL) * row,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, thread, time::Duration};
+
+    use super::{Overview, OVERLAY_ID, PAGE};
+    use crate::{
+        cues::Cues,
+        player::sim::SimPlayer,
+        playlist::{Playlist, Segment},
+        presenter::{Config, Presenter},
+        state::State,
+    };
+
+    #[test]
+    fn test_overview() {
+        // Cues every second of a 30 second video
+        let cues = (1..30)
+            .map(|x| format!(",,00:00:{x:02}:00,00:00:{x:02}:00,0,Cue Point\n"))
+            .collect::<String>();
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("talk.mp4"), "video").unwrap();
+
+        let playlist = Playlist::single(dir.join("talk.mp4"), None, cues.parse().unwrap());
+        let player = SimPlayer::new(10, 300);
+        let presenter = Presenter::with_player(Config::default(), playlist, player).unwrap();
+        let mut overview = Overview::new(&presenter);
+        // Wait for the simulated video to load, so the jump isn't queued
+        while presenter.status().unwrap().state == State::Loading {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // Nothing has been cached, so there are only the labels
+        overview.open();
+        assert_eq!(overview.selected(), Some(0));
+        assert_eq!(presenter.player().tiles(), Some(Vec::new()));
+        let ass = presenter.player().overlay(OVERLAY_ID).unwrap();
+        assert!(ass.contains("}#0\n"));
+        assert!(ass.contains("}1/2\n"));
+
+        // Moving past the end of the page shows the next one
+        overview.select(PAGE as isize + 2);
+        assert_eq!(overview.selected(), Some(22));
+        assert!(presenter
+            .player()
+            .overlay(OVERLAY_ID)
+            .unwrap()
+            .contains("}2/2\n"));
+        overview.select(100);
+        assert_eq!(overview.selected(), Some(29));
+        overview.select(-1);
+
+        overview.go();
+        assert!(!overview.is_open());
+        assert_eq!(presenter.player().tiles(), None);
+        assert_eq!(presenter.player().overlay(OVERLAY_ID), None);
+        assert_eq!(presenter.current_cue(), 28);
+    }
+
+    #[test]
+    fn test_refresh() {
+        // A still image is its own thumbnail, so it can be made without touching the cache
+        let tmp = tempfile::tempdir().unwrap();
+        let slide = tmp.path().join("slide.png");
+        let playlist = Playlist::from_segments(vec![Segment {
+            media: slide.clone(),
+            markers: None,
+            cues: Cues::still(),
+            still: true,
+        }]);
+        let player = SimPlayer::playlist(10, vec![u64::MAX]);
+        let presenter = Presenter::with_player(Config::default(), playlist, player).unwrap();
+        let mut overview = Overview::new(&presenter);
+
+        overview.open();
+        assert_eq!(presenter.player().tiles(), Some(Vec::new()));
+
+        // Thumbnails made while it is open are shown once it is refreshed
+        fs::write(&slide, "image").unwrap();
+        overview.refresh();
+        // (The start and the still's one cue are both the slide)
+        let tiles = presenter.player().tiles().unwrap();
+        assert_eq!(tiles.len(), 2);
+        assert!(tiles.iter().all(|x| x.image == slide));
+
+        // Refreshing a closed overview doesn't open it
+        overview.close();
+        overview.refresh();
+        assert_eq!(presenter.player().tiles(), None);
+    }
+}
@@ -0,0 +1,225 @@
+//! Time budgets.
+//! Tracks how long each section of a live presentation takes against the budgets in the cue file, warning on screen when one runs over.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel::RecvTimeoutError;
+
+use crate::{
+    player::{Player, OVERLAY_WIDTH},
+    playlist::Playlist,
+    presenter::Presenter,
+    session::Event,
+    state::{State, Status},
+    time::format_duration,
+    timeline::text,
+};
+
+/// Id of the pace readout's overlay, see [`Player::set_overlay`].
+/// It has its own overlay so it never hides the other messages on the OSD.
+pub const OVERLAY_ID: u32 = 3;
+/// How often the overrun warning is refreshed.
+const TICK: Duration = Duration::from_millis(500);
+/// How long the pace is shown for after each cue.
+const FLASH: Duration = Duration::from_secs(3);
+/// Space between the readout and the top right corner of the video.
+const MARGIN: u32 = 40;
+
+/// Compares the time spent in each section to its budget.
+/// A section starts when its cue becomes current and lasts until the cue changes, including any playback.
+#[derive(Debug, Clone)]
+pub struct Pace {
+    /// Budget of the section starting at each cue.
+    budgets: Vec<Option<Duration>>,
+    /// The current cue and when it was reached, once the talk has started.
+    section: Option<(usize, Duration)>,
+    /// Seconds behind (positive) or ahead of (negative) the plan over all finished sections.
+    drift: f64,
+}
+
+impl Pace {
+    /// Creates a tracker using the budgets from a playlist's cues.
+    pub fn new(playlist: &Playlist) -> Self {
+        Self {
+            budgets: (0..=playlist.len()).map(|x| playlist.budget(x)).collect(),
+            section: None,
+            drift: 0.0,
+        }
+    }
+
+    /// Records a status change that happened `at` after tracking started.
+    /// The clock only starts once the talk leaves the first cue or starts playing.
+    pub fn update(&mut self, at: Duration, status: &Status) {
+        let Some((cue, since)) = self.section else {
+            if status.cue > 0 || !matches!(status.state, State::Loading | State::Idle) {
+                self.section = Some((status.cue, at));
+            }
+            return;
+        };
+
+        if cue == status.cue {
+            return;
+        }
+
+        if let Some(budget) = self.budget(cue) {
+            self.drift += at.saturating_sub(since).as_secs_f64() - budget.as_secs_f64();
+        }
+        self.section = Some((status.cue, at));
+    }
+
+    /// The current cue and how far its section is over budget, if it is.
+    pub fn overrun(&self, now: Duration) -> Option<(usize, Duration)> {
+        let (cue, since) = self.section?;
+        let over = now
+            .saturating_sub(since)
+            .checked_sub(self.budget(cue)?)
+            .filter(|x| !x.is_zero())?;
+        Some((cue, over))
+    }
+
+    /// Seconds behind (positive) or ahead of (negative) the plan, counting the overrun of the current section.
+    pub fn pace(&self, now: Duration) -> f64 {
+        let over = self.overrun(now).map(|x| x.1.as_secs_f64());
+        self.drift + over.unwrap_or_default()
+    }
+
+    /// Starts tracking a live presentation on a background thread.
+    /// Whenever the current section is over budget, the overrun and overall pace are shown in the top right corner.
+    /// The pace is also shown briefly every time the cue changes.
+    /// Nothing is shown while the screen is blanked or once the end is reached.
+    pub fn start<P: Player + 'static>(presenter: &Presenter<P>) {
+        let start = Instant::now();
+        let mut pace = Pace::new(&presenter.playlist());
+        let statuses = presenter.watch();
+        let presenter = presenter.clone();
+
+        thread::spawn(move || {
+            let (mut last, mut shown) = (None::<Status>, None);
+            let mut flash_until = Duration::ZERO;
+            loop {
+                let now = start.elapsed();
+                match statuses.recv_timeout(TICK) {
+                    Ok(status) => {
+                        let changed = last.as_ref().is_some_and(|x| x.cue != status.cue);
+                        pace.update(now, &status);
+                        if changed && pace.section.is_some() {
+                            flash_until = now + FLASH;
+                        }
+                        last = Some(status);
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+
+                let hidden = last
+                    .as_ref()
+                    .is_some_and(|x| matches!(x.state, State::Ended | State::Blackout { .. }));
+                let readout = match pace.overrun(now) {
+                    _ if hidden => None,
+                    Some((cue, over)) => Some(format!(
+                        "#{cue} over by {} · {}",
+                        format_duration(over),
+                        format_pace(pace.pace(now))
+                    )),
+                    None if now < flash_until => {
+                        Some(format!("Pace: {}", format_pace(pace.pace(now))))
+                    }
+                    None => None,
+                };
+                if readout == shown {
+                    continue;
+                }
+
+                let ass = readout
+                    .as_deref()
+                    .map(|x| text(9, OVERLAY_WIDTH - MARGIN, MARGIN, x));
+                if let Err(e) = presenter.player().set_overlay(OVERLAY_ID, ass.as_deref()) {
+                    presenter.log(Event::Error {
+                        message: e.to_string(),
+                    });
+                }
+                shown = readout;
+            }
+        });
+    }
+
+    fn budget(&self, cue: usize) -> Option<Duration> {
+        *self.budgets.get(cue)?
+    }
+}
+
+/// Formats a pace as how far behind or ahead of the plan it is.
+fn format_pace(secs: f64) -> String {
+    let duration = Duration::from_secs_f64(secs.abs());
+    match secs {
+        _ if duration.as_secs() == 0 => "on time".to_owned(),
+        x if x > 0.0 => format!("{} behind", format_duration(duration)),
+        _ => format!("{} ahead", format_duration(duration)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{format_pace, Pace};
+    use crate::{
+        cues::Cues,
+        playlist::Playlist,
+        state::{State, Status},
+    };
+
+    fn status(state: State, cue: usize) -> Status {
+        Status {
+            state,
+            cue,
+            segment: 0,
+            fps: 30.0,
+            timeline: false,
+            speed: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_pace() {
+        let secs = Duration::from_secs;
+        let cues = "\
+            ,budget=0:20,00:00:10:00,00:00:10:00,0,Cue Point\n\
+            ,budget=5,00:00:20:00,00:00:20:00,0,Cue Point\n\
+            ,,00:00:30:00,00:00:30:00,0,Cue Point\n"
+            .parse::<Cues>()
+            .unwrap();
+        let mut pace = Pace::new(&Playlist::single("talk.mp4", None, cues));
+
+        // Waiting at the start doesn't count
+        pace.update(secs(0), &status(State::Idle, 0));
+        assert_eq!(pace.overrun(secs(100)), None);
+        pace.update(secs(100), &status(State::Playing { target: 1 }, 0));
+        pace.update(secs(110), &status(State::Held { cue: 1 }, 1));
+
+        // Cue 1 has 20s, so finishing at 125 is 5s ahead
+        assert_eq!(pace.overrun(secs(125)), None);
+        pace.update(secs(125), &status(State::Held { cue: 2 }, 2));
+        assert_eq!(pace.pace(secs(125)), -5.0);
+
+        // Cue 2 has 5s, so is 3s over by 133
+        assert_eq!(pace.overrun(secs(133)), Some((2, secs(3))));
+        assert_eq!(pace.pace(secs(133)), -2.0);
+        pace.update(secs(140), &status(State::Held { cue: 3 }, 3));
+        assert_eq!(pace.pace(secs(140)), 5.0);
+
+        // Cue 3 has no budget
+        assert_eq!(pace.overrun(secs(1000)), None);
+        assert_eq!(pace.pace(secs(1000)), 5.0);
+    }
+
+    #[test]
+    fn test_format_pace() {
+        assert_eq!(format_pace(0.4), "on time");
+        assert_eq!(format_pace(75.0), "1:15 behind");
+        assert_eq!(format_pace(-5.0), "0:05 ahead");
+    }
+}
@@ -0,0 +1,105 @@
+//! Backends that actually play the video.
+//! The presenter only talks to the player through the [`Player`] trait, so the cue logic can run against [`sim::SimPlayer`] in tests.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+
+pub mod mpv;
+pub mod sim;
+
+/// Something that can play a video.
+/// Methods take `&self` because the player is shared between the input thread and the event thread.
+pub trait Player: Send + Sync {
+    fn pause(&self) -> Result<()>;
+    fn unpause(&self) -> Result<()>;
+    fn is_paused(&self) -> Result<bool>;
+
+    /// Plays backwards (or forwards again) when unpaused.
+    fn set_reverse(&self, reverse: bool) -> Result<()>;
+
+    /// Sets the playback speed, as a multiplier of normal speed.
+    fn set_speed(&self, speed: f64) -> Result<()>;
+
+    /// Seek to an absolute position in seconds.
+    fn seek(&self, secs: f64) -> Result<()>;
+    /// Seek to the very end of the video.
+    fn seek_end(&self) -> Result<()>;
+    /// Step forward one frame, pausing playback.
+    fn frame_step(&self) -> Result<()>;
+    /// Step back one frame, pausing playback.
+    fn frame_back_step(&self) -> Result<()>;
+
+    /// Switches to another file in the playlist, starting from its beginning.
+    /// A [`PlayerEvent::Loaded`] event is sent once it is ready.
+    fn switch(&self, segment: usize) -> Result<()>;
+
+    /// The current playback position in seconds.
+    fn position(&self) -> Result<f64>;
+    /// The length of the current file in seconds.
+    fn duration(&self) -> Result<f64>;
+
+    /// The audio volume, as a percentage.
+    fn volume(&self) -> Result<f64>;
+    fn set_volume(&self, volume: f64) -> Result<()>;
+
+    /// Shows a short message over the video.
+    fn show_text(&self, msg: &str) -> Result<()>;
+    /// Draws ASS events over the video, or removes them with `None`.
+    /// Positions are in a [`OVERLAY_WIDTH`] by [`OVERLAY_HEIGHT`] space, scaled to fit the window.
+    /// Each overlay has its own `id`, so several can be shown at once, with higher ids drawn on top.
+    fn set_overlay(&self, id: u32, ass: Option<&str>) -> Result<()>;
+    /// Replaces the video with a grid of images, or shows the video again with `None`.
+    fn set_tiles(&self, tiles: Option<&[Tile]>) -> Result<()>;
+    /// Hides (or shows again) the video, leaving a black screen.
+    fn set_blackout(&self, blackout: bool) -> Result<()>;
+
+    /// Saves the current video frame to an image file.
+    fn capture(&self, path: &Path) -> Result<()>;
+    /// Covers the video for a transition, or removes the cover with `None`.
+    fn set_cover(&self, cover: Option<&Cover>) -> Result<()>;
+
+    /// Blocks the calling thread, passing every event the player emits to `handler`.
+    fn observe(&self, handler: &mut dyn FnMut(PlayerEvent)) -> !;
+}
+
+/// Width of the space [`Player::set_overlay`] draws in.
+pub const OVERLAY_WIDTH: u32 = 1920;
+/// Height of the space [`Player::set_overlay`] draws in.
+pub const OVERLAY_HEIGHT: u32 = 1080;
+
+/// What covers the video during a [transition](crate::transition).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cover {
+    /// Black, with an opacity from 0 to 1.
+    Black(f64),
+    /// An image saved with [`Player::capture`], with an opacity from 0 to 1.
+    Still(PathBuf, f64),
+}
+
+/// An image shown in place of the video, see [`Player::set_tiles`].
+/// Positioned in the same space as the overlay, so the two line up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tile {
+    pub image: PathBuf,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Events emitted by a [`Player`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerEvent {
+    /// A file has been loaded and is ready to play.
+    Loaded {
+        /// The frame rate of the video, if it could be determined.
+        fps: Option<f64>,
+        /// Human readable info about the file, as (name, value) pairs.
+        info: Vec<(&'static str, String)>,
+    },
+    /// The playback position has changed, in seconds.
+    Position(f64),
+    /// Playback has reached the end of the current file.
+    Ended,
+}
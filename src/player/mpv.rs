@@ -0,0 +1,452 @@
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+
+use libmpv::{
+    events::{Event, EventContext, PropertyData},
+    mpv_end_file_reason, FileState, GetData, Mpv, SetData,
+};
+
+use super::{Cover, Player, PlayerEvent, Tile, OVERLAY_HEIGHT, OVERLAY_WIDTH};
+use crate::{
+    error::{Error, Result},
+    presenter::Config,
+    time::Time,
+};
+
+/// How long to wait for a file to load, or a seek to finish, when grabbing frames.
+const GRAB_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An audio output, as listed by mpv.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AudioDevice {
+    /// What to pass as the `audio-device` (like `alsa/hdmi:CARD=HDMI,DEV=0`).
+    pub name: String,
+    pub description: String,
+}
+
+/// Player backed by libmpv.
+pub struct MpvPlayer {
+    mpv: Mpv,
+}
+
+impl MpvPlayer {
+    /// Creates a new mpv instance with the settings from `config` and starts loading the first file of the playlist (paused).
+    /// The rest of the playlist is queued up, so the next file is preloaded.
+    pub fn new(config: &Config, media: &[PathBuf]) -> Result<Self> {
+        // Instantiate libmpv
+        let mpv = Mpv::new().map_err(|e| Error::command("create", e))?;
+        let this = Self { mpv };
+        this.pause()?;
+
+        // Set default mpv settings
+        // These can be overridden by the user
+        if let Some(wid) = config.wid {
+            this.set_property("wid", wid as i64)?;
+        }
+        // Only ever move through the playlist when told to, but preload the next file
+        this.set_property("keep-open", "always")?;
+        this.set_property("prefetch-playlist", true)?;
+        // Still images stay up until the presenter moves on
+        this.set_property("image-display-duration", "inf")?;
+        this.set_property("osd-bar", false)?;
+        this.set_property("osc", "")?;
+        if !config.audio {
+            this.set_property("mute", "yes")?;
+        }
+        if let Some(device) = &config.audio_device {
+            this.set_property("audio-device", device.as_str())?;
+        }
+        if config.headless {
+            this.set_property("vo", "null")?;
+            this.set_property("ao", "null")?;
+        }
+
+        // Allow users to pass custom settings to mpv
+        const RESTRICTED_OPTIONS: &[&str] = &["wid", "mute"];
+
+        for (key, val) in &config.mpv_settings {
+            if RESTRICTED_OPTIONS.contains(&key.as_str()) {
+                eprintln!("[-] The mpv property `{key}` is restricted.");
+                continue;
+            }
+
+            let res = this.mpv.set_property(key, val.as_str());
+            if let Err(libmpv::Error::Raw(-8)) = res {
+                eprintln!("[-] The mpv property `{key}`, does not exist.");
+            }
+        }
+
+        this.load(media)?;
+        Ok(this)
+    }
+
+    /// Lists the audio outputs mpv can use, from its `audio-device-list` property.
+    pub fn audio_devices() -> Result<Vec<AudioDevice>> {
+        let mpv = Mpv::new().map_err(|e| Error::command("create", e))?;
+        let list = mpv
+            .get_property::<String>("audio-device-list")
+            .map_err(|e| Error::property("audio-device-list", e))?;
+
+        serde_json::from_str(&list).map_err(|e| Error::Property {
+            name: "audio-device-list".to_owned(),
+            error: e.to_string(),
+        })
+    }
+
+    /// Saves the frame at each time to an image file, using a headless mpv of its own so the presentation isn't disturbed.
+    /// The image format is picked from each file's extension, and frames are scaled down to `width` if given.
+    /// `fps` overrides the frame rate reported by the video, for converting the times to seconds.
+    pub fn grab_frames(
+        media: &Path,
+        fps: Option<f64>,
+        width: Option<u32>,
+        frames: &[(Time, PathBuf)],
+    ) -> Result<()> {
+        let mpv = Mpv::new().map_err(|e| Error::command("create", e))?;
+        let this = Self { mpv };
+        this.pause()?;
+        // Always 8 bit, so the images can be put straight into a PDF (see `crate::export`)
+        for (name, value) in [
+            ("vo", "null"),
+            ("audio", "no"),
+            ("hr-seek", "yes"),
+            ("screenshot-high-bit-depth", "no"),
+        ] {
+            this.set_property(name, value)?;
+        }
+        if let Some(width) = width {
+            this.set_property("vf", format!("scale={width}:-2").as_str())?;
+        }
+
+        let mut events = this.mpv.create_event_context();
+        this.load(&[media.to_owned()])?;
+        wait(&mut events, "loadfile", |x| matches!(x, Event::FileLoaded))?;
+        wait(&mut events, "loadfile", |x| {
+            matches!(x, Event::PlaybackRestart)
+        })?;
+
+        let fps = fps
+            .or_else(|| this.get_property("container-fps").ok())
+            .unwrap_or(60.0);
+        for (time, path) in frames {
+            this.command("seek", &[&time.as_secs(fps).to_string(), "absolute+exact"])?;
+            wait(&mut events, "seek", |x| matches!(x, Event::PlaybackRestart))?;
+            this.capture(path)?;
+        }
+        Ok(())
+    }
+
+    /// Load a playlist, replacing whatever is currently playing.
+    pub fn load(&self, media: &[PathBuf]) -> Result<()> {
+        let paths = media
+            .iter()
+            .map(|x| x.to_string_lossy())
+            .collect::<Vec<_>>();
+
+        // Not sure if this is the most concise way to do this, but its working
+        let files = paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let state = if i == 0 {
+                    FileState::Replace
+                } else {
+                    FileState::Append
+                };
+                (path.as_ref(), state, None)
+            })
+            .collect::<Vec<_>>();
+
+        self.mpv
+            .playlist_load_files(&files)
+            .map_err(|e| Error::command("loadfile", e))
+    }
+
+    /// The underlying mpv instance, for anything not covered by the [`Player`] trait.
+    pub fn mpv(&self) -> &Mpv {
+        &self.mpv
+    }
+
+    pub fn get_property<T: GetData>(&self, name: &str) -> Result<T> {
+        self.mpv
+            .get_property(name)
+            .map_err(|e| Error::property(name, e))
+    }
+
+    pub fn set_property<T: SetData>(&self, name: &str, val: T) -> Result<()> {
+        self.mpv
+            .set_property(name, val)
+            .map_err(|e| Error::property(name, e))
+    }
+
+    pub fn command(&self, name: &str, args: &[&str]) -> Result<()> {
+        self.mpv
+            .command(name, args)
+            .map_err(|e| Error::command(name, e))
+    }
+}
+
+impl Player for MpvPlayer {
+    fn pause(&self) -> Result<()> {
+        self.mpv.pause().map_err(|e| Error::command("pause", e))
+    }
+
+    fn unpause(&self) -> Result<()> {
+        self.mpv.unpause().map_err(|e| Error::command("unpause", e))
+    }
+
+    fn is_paused(&self) -> Result<bool> {
+        self.get_property("pause")
+    }
+
+    fn set_reverse(&self, reverse: bool) -> Result<()> {
+        self.set_property("play-dir", if reverse { "backward" } else { "forward" })
+    }
+
+    fn set_speed(&self, speed: f64) -> Result<()> {
+        self.set_property("speed", speed)
+    }
+
+    fn seek(&self, secs: f64) -> Result<()> {
+        self.mpv
+            .seek_absolute(secs)
+            .map_err(|e| Error::command("seek", e))
+    }
+
+    fn seek_end(&self) -> Result<()> {
+        self.mpv
+            .seek_percent_absolute(100)
+            .map_err(|e| Error::command("seek", e))
+    }
+
+    fn frame_step(&self) -> Result<()> {
+        self.mpv
+            .seek_frame()
+            .map_err(|e| Error::command("frame-step", e))
+    }
+
+    fn frame_back_step(&self) -> Result<()> {
+        self.mpv
+            .seek_frame_backward()
+            .map_err(|e| Error::command("frame-back-step", e))
+    }
+
+    fn switch(&self, segment: usize) -> Result<()> {
+        self.set_property("playlist-pos", segment as i64)
+    }
+
+    fn position(&self) -> Result<f64> {
+        self.get_property("playback-time")
+    }
+
+    fn duration(&self) -> Result<f64> {
+        self.get_property("duration")
+    }
+
+    fn volume(&self) -> Result<f64> {
+        self.get_property("volume")
+    }
+
+    fn set_volume(&self, volume: f64) -> Result<()> {
+        self.set_property("volume", volume)
+    }
+
+    fn show_text(&self, msg: &str) -> Result<()> {
+        self.command("show-text", &[&quote(msg)])
+    }
+
+    fn set_overlay(&self, id: u32, ass: Option<&str>) -> Result<()> {
+        let id = id.to_string();
+        match ass {
+            Some(ass) => self.command(
+                "osd-overlay",
+                &[
+                    &id,
+                    "ass-events",
+                    &quote(ass),
+                    &OVERLAY_WIDTH.to_string(),
+                    &OVERLAY_HEIGHT.to_string(),
+                    // Later overlays are drawn on top
+                    &id,
+                ],
+            ),
+            None => self.command("osd-overlay", &[&id, "none", r#""""#]),
+        }
+    }
+
+    fn set_tiles(&self, tiles: Option<&[Tile]>) -> Result<()> {
+        let Some(tiles) = tiles else {
+            return self.command("vf", &["remove", "@tiles"]);
+        };
+
+        // The video is replaced by a black frame the size of the overlay, so the tiles line up with it
+        let mut graph = format!(
+            "[in]scale={OVERLAY_WIDTH}:{OVERLAY_HEIGHT},setsar=1,drawbox=c=black:t=fill[t0]"
+        );
+        for (i, tile) in tiles.iter().enumerate() {
+            let Tile {
+                x,
+                y,
+                width,
+                height,
+                ..
+            } = tile;
+            let _ = write!(
+                graph,
+                ";movie='{}',scale={width}:{height}[i{i}];[t{i}][i{i}]overlay={x}:{y}[t{}]",
+                movie_path(&tile.image),
+                i + 1
+            );
+        }
+        // The last output is left unlabelled, so it becomes the output of the filter
+        graph.truncate(graph.rfind('[').unwrap_or(graph.len()));
+
+        self.command("vf", &["add", &quote(&format!("@tiles:lavfi=[{graph}]"))])
+    }
+
+    fn set_blackout(&self, blackout: bool) -> Result<()> {
+        // Covering the frame with a filter keeps the window and decoder alive, so un-blanking is instant
+        match blackout {
+            true => self.command("vf", &["add", "@blackout:lavfi=[drawbox=c=black:t=fill]"]),
+            false => self.command("vf", &["remove", "@blackout"]),
+        }
+    }
+
+    fn capture(&self, path: &Path) -> Result<()> {
+        self.command(
+            "screenshot-to-file",
+            &[&quote(&path.to_string_lossy()), "video"],
+        )
+    }
+
+    fn set_cover(&self, cover: Option<&Cover>) -> Result<()> {
+        // Like the blackout, a filter is used so it is redrawn while paused
+        // Adding a filter with the same label replaces it
+        let graph = match cover {
+            None => return self.command("vf", &["remove", "@transition"]),
+            Some(Cover::Black(alpha)) => format!("drawbox=c=black@{alpha:.3}:t=fill"),
+            Some(Cover::Still(path, alpha)) => format!(
+                "movie='{}',format=rgba,colorchannelmixer=aa={alpha:.3}[still];[in][still]overlay",
+                movie_path(path)
+            ),
+        };
+        self.command(
+            "vf",
+            &["add", &quote(&format!("@transition:lavfi=[{graph}]"))],
+        )
+    }
+
+    fn observe(&self, handler: &mut dyn FnMut(PlayerEvent)) -> ! {
+        // Listen for the playback-time event
+        // Using this we can pause playback at each cue point
+        let mut events = self.mpv.create_event_context();
+        for (name, format) in [
+            ("playback-time", libmpv::Format::Double),
+            ("eof-reached", libmpv::Format::Flag),
+        ] {
+            if let Err(e) = events.observe_property(name, format, 0) {
+                eprintln!("[-] {}", Error::property(name, e));
+            }
+        }
+
+        loop {
+            // Not sure why the api is like this
+            // The timeout is completely arbitrary, but I saw 1000 being used in the examples, so
+            let event = match events.wait_event(1000.0) {
+                Some(Ok(e)) => e,
+                Some(Err(e)) => {
+                    eprintln!("[-] Error receiving mpv event: {e}");
+                    continue;
+                }
+                None => continue,
+            };
+
+            match event {
+                // If the file has loaded, get its FPS and some info
+                Event::FileLoaded => {
+                    #[rustfmt::skip]
+                    const INFO: &[(&str, &str)] = &[
+                        ("Container FPS", "container-fps"),
+                        ("Duration",      "duration"),
+                        ("Video Format",  "video-format"),
+                        ("MPV Version",   "mpv-version"),
+                    ];
+
+                    let fps = self.get_property::<f64>("container-fps");
+                    if let Err(e) = &fps {
+                        eprintln!("[-] {e}");
+                    }
+
+                    let info = INFO
+                        .iter()
+                        .map(|(name, prop)| {
+                            let val = self
+                                .get_property::<String>(prop)
+                                .unwrap_or_else(|_| "unknown".to_owned());
+                            (*name, val)
+                        })
+                        .collect();
+
+                    handler(PlayerEvent::Loaded {
+                        fps: fps.ok(),
+                        info,
+                    });
+                }
+                Event::PropertyChange {
+                    name: "playback-time",
+                    change: PropertyData::Double(val),
+                    ..
+                } => handler(PlayerEvent::Position(val)),
+                Event::PropertyChange {
+                    name: "eof-reached",
+                    change: PropertyData::Flag(true),
+                    ..
+                } => handler(PlayerEvent::Ended),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Waits for an event, failing if the file can't be played or it takes too long.
+fn wait(events: &mut EventContext, name: &str, done: impl Fn(&Event) -> bool) -> Result<()> {
+    let error = |error: &str| Error::Command {
+        name: name.to_owned(),
+        error: error.to_owned(),
+    };
+
+    let start = Instant::now();
+    while start.elapsed() < GRAB_TIMEOUT {
+        match events.wait_event(0.5) {
+            Some(Ok(Event::EndFile(mpv_end_file_reason::Error))) => {
+                return Err(error("the file could not be played"))
+            }
+            Some(Ok(event)) if done(&event) => return Ok(()),
+            Some(Err(e)) => return Err(Error::command(name, e)),
+            _ => {}
+        }
+    }
+
+    Err(error("timed out"))
+}
+
+/// Escapes a path for the `movie` source of a lavfi filter graph.
+fn movie_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('\\', "/")
+        .replace(':', "\\:")
+}
+
+/// Quotes an argument for an mpv command string, so spaces and newlines are kept.
+fn quote(arg: &str) -> String {
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
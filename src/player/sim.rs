@@ -0,0 +1,313 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+use super::{Cover, Player, PlayerEvent, Tile};
+use crate::error::{Error, Result};
+
+/// A fake player with a simulated clock, for testing the cue logic without a real video.
+/// Time only moves when [`SimPlayer::step`] is called (or in real time from [`Player::observe`]).
+///
+/// Positions are tracked as whole frames so they line up exactly with cue times.
+pub struct SimPlayer {
+    fps: u32,
+    /// Length in frames of each file in the playlist.
+    durations: Vec<u64>,
+    state: Mutex<SimState>,
+}
+
+struct SimState {
+    segment: usize,
+    frame: u64,
+    paused: bool,
+    reverse: bool,
+    speed: f64,
+    blackout: bool,
+    volume: f64,
+    /// Every cover set, in order.
+    covers: Vec<Option<Cover>>,
+    /// Where the last frame was captured to.
+    captured: Option<PathBuf>,
+    /// Whether captures fail, see [`SimPlayer::fail_captures`].
+    capture_fails: bool,
+    /// Events caused by seeking or loading, sent on the next step.
+    pending: Vec<PlayerEvent>,
+    /// Every message passed to `show_text`.
+    messages: Vec<String>,
+    /// What is currently drawn with `set_overlay`, by id.
+    overlays: BTreeMap<u32, String>,
+    /// What is currently shown with `set_tiles`.
+    tiles: Option<Vec<Tile>>,
+}
+
+impl SimPlayer {
+    /// Creates a paused player for a video with the given frame rate and length in frames.
+    /// A [`PlayerEvent::Loaded`] event is sent on the first step.
+    pub fn new(fps: u32, duration: u64) -> Self {
+        Self::playlist(fps, vec![duration])
+    }
+
+    /// Creates a paused player for a playlist of videos, all with the same frame rate.
+    /// A still image can be simulated as a file of length [`u64::MAX`], which never ends.
+    pub fn playlist(fps: u32, durations: Vec<u64>) -> Self {
+        Self {
+            fps,
+            durations,
+            state: Mutex::new(SimState {
+                segment: 0,
+                frame: 0,
+                paused: true,
+                reverse: false,
+                speed: 1.0,
+                blackout: false,
+                volume: 100.0,
+                covers: Vec::new(),
+                captured: None,
+                capture_fails: false,
+                pending: vec![Self::loaded(fps)],
+                messages: Vec::new(),
+                overlays: BTreeMap::new(),
+                tiles: None,
+            }),
+        }
+    }
+
+    /// Moves the clock forward by some number of frames (scaled by the speed), returning the events a real player would emit.
+    /// If playing, only one position event is sent no matter how many frames were skipped, like a lagging player.
+    pub fn step(&self, frames: u64) -> Vec<PlayerEvent> {
+        let mut state = self.state.lock().unwrap();
+        let mut events = state.pending.drain(..).collect::<Vec<_>>();
+
+        if !state.paused {
+            let frames = (frames as f64 * state.speed).round() as u64;
+            let duration = self.durations[state.segment];
+            let end = match state.reverse {
+                true => 0,
+                false => duration,
+            };
+            state.frame = match state.reverse {
+                true => state.frame.saturating_sub(frames),
+                false => state.frame.saturating_add(frames).min(duration),
+            };
+            events.push(PlayerEvent::Position(self.secs(state.frame)));
+
+            // Like mpv with `keep-open`, pause on the last frame (or the first when playing backwards)
+            if state.frame == end {
+                state.paused = true;
+                events.push(PlayerEvent::Ended);
+            }
+        }
+
+        events
+    }
+
+    /// The index of the current file in the playlist.
+    pub fn segment(&self) -> usize {
+        self.state.lock().unwrap().segment
+    }
+
+    /// The current frame number.
+    pub fn frame(&self) -> u64 {
+        self.state.lock().unwrap().frame
+    }
+
+    /// The playback speed.
+    pub fn speed(&self) -> f64 {
+        self.state.lock().unwrap().speed
+    }
+
+    /// Whether playback is set to go backwards.
+    pub fn reverse(&self) -> bool {
+        self.state.lock().unwrap().reverse
+    }
+
+    /// Whether the screen is currently blanked.
+    pub fn blackout(&self) -> bool {
+        self.state.lock().unwrap().blackout
+    }
+
+    /// Every cover that has been set with [`Player::set_cover`], including removing it (`None`).
+    pub fn covers(&self) -> Vec<Option<Cover>> {
+        self.state.lock().unwrap().covers.clone()
+    }
+
+    /// Where the last frame was captured to with [`Player::capture`].
+    pub fn captured(&self) -> Option<PathBuf> {
+        self.state.lock().unwrap().captured.clone()
+    }
+
+    /// Makes every [`Player::capture`] from now on fail, like a real player that can't write the file.
+    pub fn fail_captures(&self) {
+        self.state.lock().unwrap().capture_fails = true;
+    }
+
+    /// All messages that have been shown with [`Player::show_text`].
+    pub fn messages(&self) -> Vec<String> {
+        self.state.lock().unwrap().messages.clone()
+    }
+
+    /// What is currently drawn with [`Player::set_overlay`] with the given id.
+    pub fn overlay(&self, id: u32) -> Option<String> {
+        self.state.lock().unwrap().overlays.get(&id).cloned()
+    }
+
+    /// The tiles currently shown with [`Player::set_tiles`].
+    pub fn tiles(&self) -> Option<Vec<Tile>> {
+        self.state.lock().unwrap().tiles.clone()
+    }
+
+    fn secs(&self, frame: u64) -> f64 {
+        // Computed the same way as `Time::as_secs`, so floating point errors line up
+        (frame / self.fps as u64) as f64 + (frame % self.fps as u64) as f64 / self.fps as f64
+    }
+
+    fn loaded(fps: u32) -> PlayerEvent {
+        PlayerEvent::Loaded {
+            fps: Some(fps as f64),
+            info: vec![("Container FPS", fps.to_string())],
+        }
+    }
+
+    fn set_frame(&self, frame: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.frame = frame.min(self.durations[state.segment]);
+
+        // Like mpv's property observers, only the latest position is sent
+        let pos = PlayerEvent::Position(self.secs(state.frame));
+        state
+            .pending
+            .retain(|x| !matches!(x, PlayerEvent::Position(_)));
+        state.pending.push(pos);
+    }
+}
+
+impl Player for SimPlayer {
+    fn pause(&self) -> Result<()> {
+        self.state.lock().unwrap().paused = true;
+        Ok(())
+    }
+
+    fn unpause(&self) -> Result<()> {
+        self.state.lock().unwrap().paused = false;
+        Ok(())
+    }
+
+    fn is_paused(&self) -> Result<bool> {
+        Ok(self.state.lock().unwrap().paused)
+    }
+
+    fn set_speed(&self, speed: f64) -> Result<()> {
+        self.state.lock().unwrap().speed = speed;
+        Ok(())
+    }
+
+    fn set_reverse(&self, reverse: bool) -> Result<()> {
+        self.state.lock().unwrap().reverse = reverse;
+        Ok(())
+    }
+
+    fn seek(&self, secs: f64) -> Result<()> {
+        self.set_frame((secs.max(0.0) * self.fps as f64).round() as u64);
+        Ok(())
+    }
+
+    fn seek_end(&self) -> Result<()> {
+        self.set_frame(u64::MAX);
+        Ok(())
+    }
+
+    fn switch(&self, segment: usize) -> Result<()> {
+        // Anything pending from the old file is still sent first, as a real player might
+        let mut state = self.state.lock().unwrap();
+        state.segment = segment;
+        state.frame = 0;
+        state.pending.push(Self::loaded(self.fps));
+        Ok(())
+    }
+
+    fn frame_step(&self) -> Result<()> {
+        self.pause()?;
+        self.set_frame(self.frame() + 1);
+        Ok(())
+    }
+
+    fn frame_back_step(&self) -> Result<()> {
+        self.pause()?;
+        self.set_frame(self.frame().saturating_sub(1));
+        Ok(())
+    }
+
+    fn position(&self) -> Result<f64> {
+        Ok(self.secs(self.frame()))
+    }
+
+    fn duration(&self) -> Result<f64> {
+        let state = self.state.lock().unwrap();
+        Ok(self.secs(self.durations[state.segment]))
+    }
+
+    fn volume(&self) -> Result<f64> {
+        Ok(self.state.lock().unwrap().volume)
+    }
+
+    fn set_volume(&self, volume: f64) -> Result<()> {
+        self.state.lock().unwrap().volume = volume;
+        Ok(())
+    }
+
+    fn show_text(&self, msg: &str) -> Result<()> {
+        self.state.lock().unwrap().messages.push(msg.to_owned());
+        Ok(())
+    }
+
+    fn set_overlay(&self, id: u32, ass: Option<&str>) -> Result<()> {
+        let overlays = &mut self.state.lock().unwrap().overlays;
+        match ass {
+            Some(ass) => overlays.insert(id, ass.to_owned()),
+            None => overlays.remove(&id),
+        };
+        Ok(())
+    }
+
+    fn set_tiles(&self, tiles: Option<&[Tile]>) -> Result<()> {
+        self.state.lock().unwrap().tiles = tiles.map(<[Tile]>::to_vec);
+        Ok(())
+    }
+
+    fn set_blackout(&self, blackout: bool) -> Result<()> {
+        self.state.lock().unwrap().blackout = blackout;
+        Ok(())
+    }
+
+    fn capture(&self, path: &Path) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.capture_fails {
+            return Err(Error::Command {
+                name: "screenshot-to-file".to_owned(),
+                error: "simulated failure".to_owned(),
+            });
+        }
+
+        state.captured = Some(path.to_owned());
+        Ok(())
+    }
+
+    fn set_cover(&self, cover: Option<&Cover>) -> Result<()> {
+        self.state.lock().unwrap().covers.push(cover.cloned());
+        Ok(())
+    }
+
+    fn observe(&self, handler: &mut dyn FnMut(PlayerEvent)) -> ! {
+        loop {
+            for event in self.step(1) {
+                handler(event);
+            }
+
+            thread::sleep(Duration::from_secs_f64(1.0 / self.fps as f64));
+        }
+    }
+}
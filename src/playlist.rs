@@ -0,0 +1,292 @@
+//! Presentations made of several files.
+//! A [`Playlist`] is a list of segments, each a media file with its own cues, played back to back as one continuous sequence of cues.
+//!
+//! The manifest format is one segment per line, the media file then (optionally) its cue file, separated by a comma.
+//! Still images (PNG or JPEG) can be mixed in as their own lines, each is a single cue that is shown until advanced past.
+//! Paths are relative to the manifest and anything after a `#` is a comment.
+//! ```text
+//! # media, cues
+//! intro.mp4, intro.csv
+//! agenda.png
+//! demo.mp4, demo.csv
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    cues::Cues,
+    error::{Error, Result},
+    time::{time, Time},
+};
+
+/// A single media file in a playlist.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub media: PathBuf,
+    /// The file the cues were loaded from, if any.
+    pub markers: Option<PathBuf>,
+    pub cues: Cues,
+    /// Whether the media is a still image, which has a single cue at its start and never ends.
+    pub still: bool,
+}
+
+/// The cues of every segment, numbered as one sequence.
+/// Cue 0 is the start of the first segment, then come the cues of each segment in order.
+/// The start of later segments is not a cue, so playing past the last cue of a segment continues into the next one.
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    segments: Vec<Segment>,
+}
+
+impl Playlist {
+    /// Creates a playlist of a single video.
+    pub fn single(media: impl Into<PathBuf>, markers: Option<PathBuf>, cues: Cues) -> Self {
+        Self {
+            segments: vec![Segment {
+                media: media.into(),
+                markers,
+                cues,
+                still: false,
+            }],
+        }
+    }
+
+    /// Creates a playlist from already loaded segments.
+    ///
+    /// # Panics
+    /// If there are no segments.
+    pub fn from_segments(segments: Vec<Segment>) -> Self {
+        assert!(
+            !segments.is_empty(),
+            "a playlist needs at least one segment"
+        );
+        Self { segments }
+    }
+
+    /// Loads a manifest and the cue files it lists, see the [module docs](self) for the format.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let contents = fs::read_to_string(path)?;
+
+        let mut segments = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = |message: &str| Error::Manifest {
+                line: i + 1,
+                message: message.to_owned(),
+            };
+
+            let mut parts = line.split(',').map(str::trim);
+            let media = parts.next().filter(|x| !x.is_empty());
+            let markers = parts.next().filter(|x| !x.is_empty());
+            if parts.next().is_some() {
+                return Err(error("expected `<media>, <cues>`"));
+            }
+
+            let media = dir.join(media.ok_or_else(|| error("missing media file"))?);
+            let still = is_image(&media);
+            let markers = markers.map(|x| dir.join(x));
+            let cues = match &markers {
+                Some(_) if still => return Err(error("images can't have a cue file")),
+                Some(markers) => Cues::from_file(markers)?,
+                None if still => Cues::still(),
+                None => Cues::default(),
+            };
+
+            segments.push(Segment {
+                media,
+                markers,
+                cues,
+                still,
+            });
+        }
+
+        if segments.is_empty() {
+            return Err(Error::Manifest {
+                line: 0,
+                message: "no media files".to_owned(),
+            });
+        }
+
+        Ok(Self { segments })
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Creates a copy of the playlist with the cues of one segment replaced.
+    pub fn with_cues(&self, segment: usize, cues: Cues) -> Self {
+        let mut this = self.clone();
+        this.segments[segment].cues = cues;
+        this
+    }
+
+    /// Gets the total number of cues, across all segments.
+    pub fn len(&self) -> usize {
+        self.segments.iter().map(|x| x.cues.len()).sum()
+    }
+
+    /// Checks if there are no cues in any segment.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Uses the current time (in seconds) within a segment and the fps to get the current cue.
+    /// Before the first cue of a segment, this is the last cue of the one before.
+    pub fn current(&self, segment: usize, time: f64, fps: f64) -> usize {
+        self.offset(segment) + self.segments[segment].cues.current(time, fps)
+    }
+
+    /// Gets the segment a cue is in and its time within that segment.
+    /// Anything past the last cue is [`Time::END`] of the last segment.
+    pub fn get(&self, cue: usize) -> (usize, Time) {
+        if cue == 0 {
+            return (0, time!(00:00:00:00));
+        }
+
+        let mut offset = 0;
+        for (i, segment) in self.segments.iter().enumerate() {
+            if cue <= offset + segment.cues.len() {
+                return (i, segment.cues.get(cue - offset));
+            }
+            offset += segment.cues.len();
+        }
+
+        (self.segments.len() - 1, Time::END)
+    }
+
+    /// Gets the planned duration of the section starting at a cue, if it has one.
+    pub fn budget(&self, cue: usize) -> Option<Duration> {
+        let (segment, _) = self.get(cue);
+        self.segments[segment]
+            .cues
+            .budget(cue.checked_sub(self.offset(segment))?)
+    }
+
+    /// Gets the playback speed of the section starting at a cue, if it has one.
+    pub fn speed(&self, cue: usize) -> Option<f64> {
+        let (segment, _) = self.get(cue);
+        self.segments[segment]
+            .cues
+            .speed(cue.checked_sub(self.offset(segment))?)
+    }
+
+    /// Gets the marker name of a cue, if it has one.
+    pub fn name(&self, cue: usize) -> Option<&str> {
+        let (segment, _) = self.get(cue);
+        self.segments[segment]
+            .cues
+            .name(cue.checked_sub(self.offset(segment))?)
+    }
+
+    /// Gets the marker description of a cue, if it has one.
+    pub fn notes(&self, cue: usize) -> Option<&str> {
+        let (segment, _) = self.get(cue);
+        self.segments[segment]
+            .cues
+            .notes(cue.checked_sub(self.offset(segment))?)
+    }
+
+    /// Checks if any cue has a budget.
+    pub fn has_budgets(&self) -> bool {
+        self.segments.iter().any(|x| x.cues.has_budgets())
+    }
+
+    /// The number of cues in all segments before this one.
+    fn offset(&self, segment: usize) -> usize {
+        self.segments[..segment].iter().map(|x| x.cues.len()).sum()
+    }
+}
+
+/// Checks if a file is a still image, from its extension.
+fn is_image(path: &Path) -> bool {
+    const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg"];
+
+    path.extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| EXTENSIONS.contains(&x.to_ascii_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{Playlist, Segment};
+    use crate::time::{time, Time};
+
+    /// Two videos at 10 fps, with cues at 1s and 2s, and at 0.5s.
+    fn playlist() -> Playlist {
+        let segment = |media: &str, cues: &str| Segment {
+            media: media.into(),
+            markers: None,
+            cues: cues.parse().unwrap(),
+            still: false,
+        };
+
+        Playlist::from_segments(vec![
+            segment(
+                "a.mp4",
+                ",,00:00:01:00,00:00:01:00,0,Cue Point\n\
+                     ,,00:00:02:00,00:00:02:00,0,Cue Point\n",
+            ),
+            segment("b.mp4", ",budget=5,00:00:00:05,00:00:00:05,0,Cue Point\n"),
+        ])
+    }
+
+    #[test]
+    fn test_cue_numbering() {
+        let playlist = playlist();
+        assert_eq!(playlist.len(), 3);
+
+        assert_eq!(playlist.get(0), (0, time!(00:00:00:00)));
+        assert_eq!(playlist.get(2), (0, time!(00:00:02:00)));
+        assert_eq!(playlist.get(3), (1, time!(00:00:00:05)));
+        assert_eq!(playlist.get(4), (1, Time::END));
+
+        // The start of the second video is still the last cue of the first
+        assert_eq!(playlist.current(0, 1.5, 10.0), 1);
+        assert_eq!(playlist.current(1, 0.0, 10.0), 2);
+        assert_eq!(playlist.current(1, 0.5, 10.0), 3);
+
+        assert_eq!(playlist.budget(2), None);
+        assert_eq!(playlist.budget(3), Some(std::time::Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_manifest() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("a.csv"), ",,00:00:01:00,00:00:01:00,0,Cue Point\n").unwrap();
+        fs::write(
+            dir.join("talk.txt"),
+            "# media, cues\na.mp4, a.csv\n\nb.mp4 # no cues\nslide.PNG\n",
+        )
+        .unwrap();
+        fs::write(dir.join("bad.txt"), "a.mp4, a.csv, extra\n").unwrap();
+
+        let playlist = Playlist::from_file(dir.join("talk.txt")).unwrap();
+        let segments = playlist.segments();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].media, dir.join("a.mp4"));
+        assert_eq!(segments[0].markers, Some(dir.join("a.csv")));
+        assert_eq!(segments[1].markers, None);
+        assert!(!segments[1].still);
+        assert!(segments[2].still);
+
+        // The image is a cue of its own, right at its start
+        assert_eq!(playlist.len(), 2);
+        assert_eq!(playlist.get(2), (2, time!(00:00:00:00)));
+
+        assert!(Playlist::from_file(dir.join("bad.txt")).is_err());
+    }
+}
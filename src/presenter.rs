@@ -0,0 +1,287 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    thread,
+};
+
+use crossbeam::channel::{self, Receiver, Sender};
+
+use crate::{
+    cues::Cues,
+    error::Result,
+    player::{mpv::MpvPlayer, Player},
+    playlist::Playlist,
+    session::{Event, SessionLog},
+    state::{Command, Machine, Message, Status},
+    transition::Transition,
+};
+
+/// Everything needed to start a presentation.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Path of the video file to play.
+    pub video: PathBuf,
+    /// Marker file to load the cues from, see [`Cues`] for the format.
+    pub markers: PathBuf,
+    /// Manifest of several media and cue files to play as one presentation, see [`Playlist`] for the format.
+    /// If set, `video` and `markers` are ignored.
+    pub playlist: Option<PathBuf>,
+    /// Settings passed directly to mpv, as key value pairs.
+    pub mpv_settings: Vec<(String, String)>,
+    /// Whether to play audio or not.
+    pub audio: bool,
+    /// The mpv name of the audio output to use, see [`MpvPlayer::audio_devices`].
+    /// If not set, mpv picks one.
+    pub audio_device: Option<String>,
+    /// Transition to hide the cut when jumping between cues, if any.
+    pub transition: Option<Transition>,
+    /// Whether going back plays backwards to the previous cue, rather than jumping to it.
+    pub rewind: bool,
+    /// Frame rate to use for cue times, instead of the one reported by the player.
+    pub fps: Option<f64>,
+    /// Id of the window mpv should render into.
+    /// If not set, mpv will create its own window.
+    pub wid: Option<u64>,
+    /// Run without any video or audio output, for automated runs.
+    pub headless: bool,
+    /// File to append the [session log](crate::session) to.
+    /// If not set, events are only printed to the console.
+    pub log: Option<PathBuf>,
+}
+
+impl Config {
+    /// The file the presentation is started from, the playlist manifest if there is one, otherwise the video.
+    pub fn source(&self) -> &Path {
+        self.playlist.as_deref().unwrap_or(&self.video)
+    }
+
+    /// Loads the playlist manifest, or the cues of the single video if there isn't one.
+    pub fn load_playlist(&self) -> Result<Playlist> {
+        Ok(match &self.playlist {
+            Some(path) => Playlist::from_file(path)?,
+            None => Playlist::single(
+                &self.video,
+                Some(self.markers.clone()),
+                Cues::from_file(&self.markers)?,
+            ),
+        })
+    }
+}
+
+/// Sent to subscribers whenever the current cue changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CueEvent {
+    /// The cue that was current before the change.
+    pub from: usize,
+    /// The new current cue.
+    pub to: usize,
+    /// Whether the cue was reached by playing into it, rather than by seeking.
+    pub reached: bool,
+}
+
+/// Plays a video, pausing at each cue.
+/// Clones share the same presentation, so one can be handed to each thread that needs to control it.
+///
+/// The presentation state lives on its own thread (see [`Machine`]), so the methods here just send it commands.
+/// Errors while handling a command are logged and shown on screen, rather than returned.
+pub struct Presenter<P: Player = MpvPlayer> {
+    config: Config,
+    /// Shared by all clones, so a reload is seen by all of them.
+    playlist: Arc<RwLock<Arc<Playlist>>>,
+    player: Arc<P>,
+    log: Arc<SessionLog>,
+    tx: Sender<Message>,
+}
+
+// Not derived, as that would require the player to be `Clone`
+impl<P: Player> Clone for Presenter<P> {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            playlist: self.playlist.clone(),
+            player: self.player.clone(),
+            log: self.log.clone(),
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl Presenter<MpvPlayer> {
+    /// Loads the cues (or playlist) and starts mpv with the settings from `config`.
+    pub fn new(config: Config) -> Result<Self> {
+        let playlist = config.load_playlist()?;
+        let media = playlist
+            .segments()
+            .iter()
+            .map(|x| x.media.clone())
+            .collect::<Vec<_>>();
+        let player = MpvPlayer::new(&config, &media)?;
+        Self::with_player(config, playlist, player)
+    }
+}
+
+impl<P: Player + 'static> Presenter<P> {
+    /// Creates a presenter using an already loaded playlist and player.
+    /// The player must already be loading the playlist's media.
+    /// This opens the session log and starts the state machine thread and a thread forwarding the player's events to it.
+    pub fn with_player(config: Config, playlist: Playlist, player: P) -> Result<Self> {
+        let log = match &config.log {
+            Some(path) => SessionLog::open(path)?,
+            None => SessionLog::console(),
+        };
+
+        log.log(Event::Start {
+            video: config.video.clone(),
+            markers: config.markers.clone(),
+            playlist: config.playlist.clone(),
+        });
+        for segment in playlist.segments() {
+            log.log(Event::CuesLoaded {
+                file: segment.markers.clone(),
+                cues: segment.cues.iter().map(|x| x.to_string()).collect(),
+            });
+        }
+
+        let (tx, rx) = channel::unbounded();
+        let this = Self {
+            config,
+            playlist: Arc::new(RwLock::new(Arc::new(playlist))),
+            player: Arc::new(player),
+            log: Arc::new(log),
+            tx,
+        };
+
+        let machine = Machine::new(this.player.clone(), this.playlist(), this.log.clone())
+            .with_fps(this.config.fps)
+            .with_transition(this.config.transition)
+            .with_rewind(this.config.rewind);
+        thread::spawn(move || machine.run(rx));
+
+        let (player, tx) = (this.player.clone(), this.tx.clone());
+        thread::spawn(move || {
+            player.observe(&mut |event| {
+                let _ = tx.send(Message::Player(event));
+            })
+        });
+
+        Ok(this)
+    }
+}
+
+impl<P: Player> Presenter<P> {
+    // == Navigation ==
+
+    /// Sends a command to the state machine.
+    pub fn send(&self, cmd: Command) {
+        let _ = self.tx.send(Message::Command(cmd));
+    }
+
+    /// Continue playing if paused at a cue, otherwise skip to the next cue.
+    /// This is what the space key does.
+    pub fn advance(&self) {
+        self.send(Command::Advance);
+    }
+
+    /// Pause and jump to the next cue.
+    pub fn next(&self) {
+        self.send(Command::Next);
+    }
+
+    /// Pause and jump to the previous cue.
+    pub fn back(&self) {
+        self.send(Command::Back);
+    }
+
+    /// Pause and jump to a specific cue.
+    /// Cue 0 is the start of the video and anything past the last cue is the end.
+    pub fn goto(&self, cue: usize) {
+        self.send(Command::Goto(cue));
+    }
+
+    /// Replaces the cues of a playlist segment, like after its cue file has been edited.
+    /// The current cue is worked out again from the playback position.
+    pub fn reload_cues(&self, segment: usize, cues: Cues) {
+        let mut playlist = self.playlist.write().unwrap();
+        self.log(Event::CuesLoaded {
+            file: playlist.segments()[segment].markers.clone(),
+            cues: cues.iter().map(|x| x.to_string()).collect(),
+        });
+
+        *playlist = Arc::new(playlist.with_cues(segment, cues));
+        let _ = self.tx.send(Message::Reload(playlist.clone()));
+    }
+
+    /// Returns a channel that receives an event every time the current cue changes.
+    /// Dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> Receiver<CueEvent> {
+        let (tx, rx) = channel::unbounded();
+        let _ = self.tx.send(Message::Subscribe(tx));
+        rx
+    }
+
+    /// Returns a channel that receives the current status, and then a new one every time the state or cue changes.
+    /// Dropping the receiver unsubscribes.
+    pub fn watch(&self) -> Receiver<Status> {
+        let (tx, rx) = channel::unbounded();
+        let _ = self.tx.send(Message::Watch(tx));
+        rx
+    }
+
+    /// Asks the state machine for its current state.
+    /// Blocks until any commands sent before this one have been handled.
+    pub fn status(&self) -> Option<Status> {
+        let (tx, rx) = channel::bounded(1);
+        self.tx.send(Message::Status(tx)).ok()?;
+        rx.recv().ok()
+    }
+
+    /// Display a message on the screen using the player's OSD.
+    /// Failing to show a message is not worth interrupting anything over, so errors are only logged.
+    pub fn info(&self, msg: impl AsRef<str>) {
+        if let Err(e) = self.player.show_text(msg.as_ref()) {
+            self.log(Event::Error {
+                message: e.to_string(),
+            });
+        }
+    }
+
+    /// Records an event in the session log.
+    pub fn log(&self, event: Event) {
+        self.log.log(event);
+    }
+
+    // == Info getters ==
+
+    /// The config the presenter was started with.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The media and cues being presented.
+    /// This is a snapshot, it won't change if the cues are reloaded.
+    pub fn playlist(&self) -> Arc<Playlist> {
+        self.playlist.read().unwrap().clone()
+    }
+
+    /// The player backend, for anything not covered by the presenter.
+    pub fn player(&self) -> &P {
+        &self.player
+    }
+
+    /// Index of the current cue.
+    /// 0 is before the first cue and `cues().len() + 1` is the end of the video.
+    pub fn current_cue(&self) -> usize {
+        self.status().map(|x| x.cue).unwrap_or_default()
+    }
+
+    /// Get the name of the video, from the file name
+    /// This is used to display the video name in the window title
+    pub fn video_name(&self) -> Cow<'_, str> {
+        let source = self.config.source();
+        match source.file_name() {
+            Some(name) => name.to_string_lossy(),
+            None => source.to_string_lossy(),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Project files.
+//! Everything needed to give a presentation can be kept in one TOML file, rather than passed on the command line every time.
+//! Paths are relative to the project file, and every field is optional apart from the media and cues (or a playlist).
+//! ```toml
+//! media = "talk.mp4"
+//! cues = "talk.csv"
+//! format = "premiere"
+//! fps = 29.97
+//! audio = true
+//! audio_device = "alsa/hdmi"
+//! volume = 80
+//! fade = 0.3
+//! monitor = 1
+//! rewind = true
+//!
+//! [keys]
+//! PageDown = "advance"
+//! PageUp = "back"
+//!
+//! [transition]
+//! kind = "fade"
+//! duration = 0.4
+//!
+//! [music]
+//! file = "bed.mp3"
+//! volume = 40
+//!
+//! [mpv]
+//! hwdec = "auto"
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    result,
+};
+
+use serde::{de, Deserialize, Deserializer};
+
+use crate::{
+    error::{Error, Result},
+    music::MusicConfig,
+    presenter::Config,
+    state::Command,
+    transition::Transition,
+};
+
+/// The settings of a presentation, loaded from a project file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Project {
+    /// The video to play.
+    pub media: Option<PathBuf>,
+    /// The marker file to load the cues from.
+    pub cues: Option<PathBuf>,
+    /// A playlist manifest, instead of `media` and `cues`.
+    pub playlist: Option<PathBuf>,
+    /// The format of the cue file, only Premiere markers are supported for now.
+    pub format: CueFormat,
+    /// Frame rate to use for cue times, instead of the one reported by the video.
+    pub fps: Option<f64>,
+    /// Whether to play audio or not.
+    pub audio: bool,
+    /// The audio output to use, see `--list-audio-devices`.
+    pub audio_device: Option<String>,
+    /// Playback volume, as a percentage.
+    pub volume: Option<u32>,
+    /// Seconds to fade the audio out before each cue and back in when resuming.
+    pub fade: Option<f64>,
+    /// Whether going back plays backwards to the previous cue, rather than jumping to it.
+    pub rewind: bool,
+    /// Transition to hide the cut when jumping between cues, see [`crate::transition`].
+    pub transition: Option<Transition>,
+    /// Music to loop in the background, see [`crate::music`].
+    pub music: Option<MusicConfig>,
+    /// Index of the monitor to show the presentation fullscreen on.
+    pub monitor: Option<usize>,
+    /// Extra key bindings, from key name to command (like `PageDown = "advance"`).
+    /// These replace the default action of the same key.
+    #[serde(deserialize_with = "parse_keys")]
+    pub keys: Vec<(String, Command)>,
+    /// Settings passed directly to mpv.
+    pub mpv: BTreeMap<String, toml::Value>,
+}
+
+/// The format of the cue file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CueFormat {
+    /// Markers exported from Premiere Pro or After Effects, see [`crate::cues::Cues`].
+    #[default]
+    Premiere,
+}
+
+impl Project {
+    /// Loads a project file, making its paths relative to the file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let error = |message: String| Error::Project {
+            file: path.to_owned(),
+            message,
+        };
+
+        let mut this = toml::from_str::<Self>(&fs::read_to_string(path)?)
+            .map_err(|e| error(e.message().to_owned()))?;
+
+        match (&this.media, &this.cues, &this.playlist) {
+            (Some(_), Some(_), None) | (None, None, Some(_)) => {}
+            (_, _, Some(_)) => return Err(error("`playlist` replaces `media` and `cues`".into())),
+            _ => return Err(error("expected `media` and `cues`, or `playlist`".into())),
+        }
+
+        let music = this.music.as_mut().map(|x| &mut x.file);
+        for path in [&mut this.media, &mut this.cues, &mut this.playlist]
+            .into_iter()
+            .flatten()
+            .chain(music)
+        {
+            *path = dir.join(&*path);
+        }
+
+        Ok(this)
+    }
+
+    /// Gets the presenter config for the project.
+    /// Anything only set on the command line (like the window to render into) is left as the default.
+    pub fn config(&self) -> Config {
+        let mut mpv_settings = self
+            .mpv
+            .iter()
+            .map(|(key, value)| (key.clone(), mpv_value(value)))
+            .collect::<Vec<_>>();
+        if let Some(volume) = self.volume {
+            mpv_settings.push(("volume".into(), volume.to_string()));
+        }
+
+        Config {
+            video: self.media.clone().unwrap_or_default(),
+            markers: self.cues.clone().unwrap_or_default(),
+            playlist: self.playlist.clone(),
+            mpv_settings,
+            audio: self.audio,
+            audio_device: self.audio_device.clone(),
+            fps: self.fps,
+            transition: self.transition,
+            rewind: self.rewind,
+            ..Default::default()
+        }
+    }
+}
+
+/// Converts a TOML value to the string mpv expects.
+fn mpv_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(x) => x.clone(),
+        toml::Value::Boolean(x) => if *x { "yes" } else { "no" }.to_owned(),
+        x => x.to_string(),
+    }
+}
+
+/// Parses the commands of the key bindings, so a typo is caught when loading rather than when the key is pressed.
+fn parse_keys<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> result::Result<Vec<(String, Command)>, D::Error> {
+    BTreeMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, cmd)| Ok((key, cmd.parse().map_err(de::Error::custom)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{CueFormat, Project};
+    use crate::{music::MusicConfig, state::Command};
+
+    #[test]
+    fn test_project() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(
+            dir.join("talk.toml"),
+            indoc::indoc! {r#"
+                media = "talk.mp4"
+                cues = "talk.csv"
+                format = "premiere"
+                fps = 25
+                volume = 80
+
+                [music]
+                file = "bed.mp3"
+
+                [keys]
+                PageDown = "advance"
+                F5 = "goto 0"
+
+                [mpv]
+                hwdec = "auto"
+                fullscreen = true
+            "#},
+        )
+        .unwrap();
+        fs::write(dir.join("bad.toml"), "media = \"talk.mp4\"\n").unwrap();
+        fs::write(
+            dir.join("typo.toml"),
+            "playlist = \"talk.txt\"\n[keys]\nSpace = \"advanse\"\n",
+        )
+        .unwrap();
+
+        let project = Project::from_file(dir.join("talk.toml")).unwrap();
+        assert_eq!(project.format, CueFormat::Premiere);
+        assert_eq!(
+            project.music,
+            Some(MusicConfig {
+                file: dir.join("bed.mp3"),
+                volume: MusicConfig::default_volume(),
+                duck: MusicConfig::default_duck(),
+            })
+        );
+        assert_eq!(
+            project.keys,
+            [
+                ("F5".to_owned(), Command::Goto(0)),
+                ("PageDown".to_owned(), Command::Advance)
+            ]
+        );
+
+        let config = project.config();
+        assert_eq!(config.video, dir.join("talk.mp4"));
+        assert_eq!(config.markers, dir.join("talk.csv"));
+        assert_eq!(config.fps, Some(25.0));
+        assert_eq!(
+            config.mpv_settings,
+            [
+                ("fullscreen".to_owned(), "yes".to_owned()),
+                ("hwdec".to_owned(), "auto".to_owned()),
+                ("volume".to_owned(), "80".to_owned())
+            ]
+        );
+
+        // Missing cues, and an unknown command
+        assert!(Project::from_file(dir.join("bad.toml")).is_err());
+        assert!(Project::from_file(dir.join("typo.toml")).is_err());
+    }
+}
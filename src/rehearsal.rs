@@ -0,0 +1,411 @@
+//! Rehearsal timing.
+//! Records when the presentation is paused, resumed and advanced, and turns that into a per-cue report.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel::RecvTimeoutError;
+use serde::Serialize;
+
+use crate::{
+    error::Result,
+    player::Player,
+    presenter::Presenter,
+    script::Script,
+    session::Event,
+    state::{State, Status},
+    time::format_duration,
+    timeline::text,
+};
+
+/// Id of the timer's overlay, see [`Player::set_overlay`].
+/// It has its own overlay so it never hides the other messages on the OSD, or the [pace](crate::pace::OVERLAY_ID).
+pub const OVERLAY_ID: u32 = 4;
+/// How often the timer is refreshed.
+const TICK: Duration = Duration::from_millis(500);
+/// Space between the timer and the top left corner of the video.
+const MARGIN: u32 = 40;
+
+/// The kinds of events recorded during a rehearsal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordKind {
+    Pause,
+    Resume,
+    Advance,
+    Back,
+    /// The end of the video was reached.
+    End,
+}
+
+/// A single timestamped event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Record {
+    /// Seconds since the rehearsal started.
+    pub at: f64,
+    pub kind: RecordKind,
+    /// The cue that was current after the event.
+    pub cue: usize,
+}
+
+/// Records the timeline of a rehearsal.
+#[derive(Debug, Clone, Default)]
+pub struct Rehearsal {
+    /// Target time to spend at each cue, if any.
+    targets: Option<Script>,
+    records: Vec<Record>,
+    /// Time spent held and total time, for each cue.
+    dwell: Vec<(Duration, Duration)>,
+
+    last: Option<(Duration, Status)>,
+}
+
+/// A rehearsal being recorded in the background, see [`Rehearsal::start`].
+pub struct Recording {
+    start: Instant,
+    rehearsal: Arc<Mutex<Rehearsal>>,
+}
+
+/// Timing for a single cue.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CueReport {
+    pub cue: usize,
+    /// Seconds spent paused at the cue.
+    pub dwell: f64,
+    /// Seconds from reaching the cue to leaving it, including playback.
+    pub total: f64,
+    /// Planned seconds to spend paused at the cue.
+    pub target: Option<f64>,
+    /// How many seconds over (positive) or under (negative) the target the dwell time was.
+    pub over: Option<f64>,
+}
+
+/// The result of a rehearsal.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    /// Total seconds from the start to the end of the rehearsal.
+    pub total: f64,
+    /// Sum of all cue targets, in seconds.
+    pub target: Option<f64>,
+    /// How many seconds over (positive) or under (negative) the total target dwell time the rehearsal was.
+    pub over: Option<f64>,
+    pub cues: Vec<CueReport>,
+    pub records: Vec<Record>,
+}
+
+impl Rehearsal {
+    /// Creates an empty rehearsal, optionally comparing against the delays in a timing script.
+    pub fn new(targets: Option<Script>) -> Self {
+        Self {
+            targets,
+            ..Default::default()
+        }
+    }
+
+    /// Records a status change that happened `at` after the start of the rehearsal.
+    pub fn record(&mut self, at: Duration, status: Status) {
+        if let Some((last_at, last)) = self.last.take() {
+            self.accumulate(&last, at.saturating_sub(last_at));
+
+            let kind = match (&last.state, &status.state) {
+                (State::Ended, State::Ended) => None,
+                (_, State::Ended) => Some(RecordKind::End),
+                _ if status.cue > last.cue => Some(RecordKind::Advance),
+                _ if status.cue < last.cue => Some(RecordKind::Back),
+                (State::Playing { .. }, State::Playing { .. }) => None,
+                (State::Playing { .. }, _) => Some(RecordKind::Pause),
+                (_, State::Playing { .. }) => Some(RecordKind::Resume),
+                _ => None,
+            };
+
+            if let Some(kind) = kind {
+                self.records.push(Record {
+                    at: at.as_secs_f64(),
+                    kind,
+                    cue: status.cue,
+                });
+            }
+        }
+
+        self.last = Some((at, status));
+    }
+
+    /// Time spent paused at the current cue so far, and its target.
+    pub fn elapsed(&self, now: Duration) -> Option<(usize, Duration, Option<Duration>)> {
+        let (at, status) = self.last.as_ref()?;
+        let mut dwell = self.dwell.get(status.cue).map(|x| x.0).unwrap_or_default();
+        if is_held(&status.state) {
+            dwell += now.saturating_sub(*at);
+        }
+
+        Some((status.cue, dwell, self.target(status.cue)))
+    }
+
+    /// Finishes the timeline at `end`, or where the video ended if it did, and summarizes it.
+    pub fn report(&self, mut end: Duration) -> Report {
+        let mut this = self.clone();
+        if let Some((at, last)) = this.last.take() {
+            if last.state == State::Ended {
+                end = at;
+            }
+            this.accumulate(&last, end.saturating_sub(at));
+        }
+
+        let cues = this
+            .dwell
+            .iter()
+            .enumerate()
+            .map(|(cue, (dwell, total))| {
+                let target = this.target(cue);
+                CueReport {
+                    cue,
+                    dwell: dwell.as_secs_f64(),
+                    total: total.as_secs_f64(),
+                    target: target.map(|x| x.as_secs_f64()),
+                    over: target.map(|x| dwell.as_secs_f64() - x.as_secs_f64()),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let target = this.targets.as_ref().map(|x| x.total().as_secs_f64());
+        let dwell = cues.iter().map(|x| x.dwell).sum::<f64>();
+        Report {
+            total: end.as_secs_f64(),
+            target,
+            over: target.map(|x| dwell - x),
+            cues,
+            records: this.records,
+        }
+    }
+
+    /// Starts recording a live presentation on a background thread.
+    /// While held at a cue, the time spent there (and the target, if any) is shown in the top left corner.
+    /// It is hidden while the screen is blanked.
+    pub fn start<P: Player + 'static>(
+        presenter: &Presenter<P>,
+        targets: Option<Script>,
+    ) -> Recording {
+        let start = Instant::now();
+        let rehearsal = Arc::new(Mutex::new(Rehearsal::new(targets)));
+        let statuses = presenter.watch();
+
+        let (presenter, this) = (presenter.clone(), rehearsal.clone());
+        thread::spawn(move || {
+            let mut shown = None;
+            loop {
+                let status = statuses.recv_timeout(TICK);
+                let mut rehearsal = this.lock().unwrap();
+                let now = start.elapsed();
+
+                match status {
+                    Ok(status) => rehearsal.record(now, status),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+
+                let held = rehearsal.last.as_ref().is_some_and(|(_, x)| {
+                    is_held(&x.state) && !matches!(x.state, State::Blackout { .. })
+                });
+                let timer = match (held, rehearsal.elapsed(now)) {
+                    (true, Some((cue, dwell, target))) => {
+                        let mut msg = format!("#{cue} {}", format_duration(dwell));
+                        if let Some(target) = target {
+                            let _ = write!(msg, " / {}", format_duration(target));
+                        }
+                        Some(msg)
+                    }
+                    _ => None,
+                };
+                drop(rehearsal);
+                if timer == shown {
+                    continue;
+                }
+
+                let ass = timer.as_deref().map(|x| text(7, MARGIN, MARGIN, x));
+                if let Err(e) = presenter.player().set_overlay(OVERLAY_ID, ass.as_deref()) {
+                    presenter.log(Event::Error {
+                        message: e.to_string(),
+                    });
+                }
+                shown = timer;
+            }
+        });
+
+        Recording { start, rehearsal }
+    }
+
+    fn accumulate(&mut self, status: &Status, time: Duration) {
+        if self.dwell.len() <= status.cue {
+            self.dwell.resize(status.cue + 1, Default::default());
+        }
+
+        let dwell = &mut self.dwell[status.cue];
+        dwell.1 += time;
+        if is_held(&status.state) {
+            dwell.0 += time;
+        }
+    }
+
+    fn target(&self, cue: usize) -> Option<Duration> {
+        self.targets.as_ref()?.get(cue)
+    }
+}
+
+impl Recording {
+    /// Summarizes the rehearsal so far.
+    pub fn report(&self) -> Report {
+        self.rehearsal.lock().unwrap().report(self.start.elapsed())
+    }
+}
+
+impl Report {
+    /// Writes the report to `<path>.json` and `<path>.csv`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path.with_extension("json"), self.to_json())?;
+        fs::write(path.with_extension("csv"), self.to_csv())?;
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("report is always serializable")
+    }
+
+    /// One row per cue, followed by a row with the totals.
+    pub fn to_csv(&self) -> String {
+        let opt = |x: Option<f64>| x.map(|x| format!("{x:.3}")).unwrap_or_default();
+
+        let mut out = String::from("cue,dwell,total,target,over\n");
+        for cue in &self.cues {
+            let _ = writeln!(
+                out,
+                "{},{:.3},{:.3},{},{}",
+                cue.cue,
+                cue.dwell,
+                cue.total,
+                opt(cue.target),
+                opt(cue.over)
+            );
+        }
+
+        let dwell = self.cues.iter().map(|x| x.dwell).sum::<f64>();
+        let _ = writeln!(
+            out,
+            "total,{dwell:.3},{:.3},{},{}",
+            self.total,
+            opt(self.target),
+            opt(self.over)
+        );
+        out
+    }
+}
+
+/// Whether the presenter is stopped at a cue, waiting for the speaker.
+fn is_held(state: &State) -> bool {
+    matches!(
+        state,
+        State::Idle | State::Held { .. } | State::Blackout { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{RecordKind, Rehearsal};
+    use crate::state::{State, Status};
+
+    fn status(state: State, cue: usize) -> Status {
+        Status {
+            state,
+            cue,
+            segment: 0,
+            fps: 30.0,
+            timeline: false,
+            speed: 1.0,
+        }
+    }
+
+    /// Cue 0 for 2s, play for 3s, cue 1 for 10s with a pause, then on to the end.
+    fn rehearsal() -> Rehearsal {
+        let secs = Duration::from_secs;
+        let mut rehearsal = Rehearsal::new(Some("0 1\n1 8".parse().unwrap()));
+
+        rehearsal.record(secs(0), status(State::Idle, 0));
+        rehearsal.record(secs(2), status(State::Playing { target: 1 }, 0));
+        rehearsal.record(secs(5), status(State::Held { cue: 1 }, 1));
+        rehearsal.record(secs(9), status(State::Playing { target: 2 }, 1));
+        rehearsal.record(secs(10), status(State::Held { cue: 1 }, 1));
+        rehearsal.record(secs(16), status(State::Playing { target: 2 }, 1));
+        rehearsal.record(secs(20), status(State::Ended, 1));
+        rehearsal
+    }
+
+    #[test]
+    fn test_records() {
+        let kinds = rehearsal()
+            .records
+            .iter()
+            .map(|x| (x.at, x.kind, x.cue))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            kinds,
+            [
+                (2.0, RecordKind::Resume, 0),
+                (5.0, RecordKind::Advance, 1),
+                (9.0, RecordKind::Resume, 1),
+                (10.0, RecordKind::Pause, 1),
+                (16.0, RecordKind::Resume, 1),
+                (20.0, RecordKind::End, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        // Time after the end isn't counted
+        let report = rehearsal().report(Duration::from_secs(21));
+
+        assert_eq!(report.total, 20.0);
+        assert_eq!(report.target, Some(9.0));
+        assert_eq!(report.over, Some(3.0));
+
+        let cues = report
+            .cues
+            .iter()
+            .map(|x| (x.cue, x.dwell, x.total, x.over))
+            .collect::<Vec<_>>();
+        assert_eq!(cues, [(0, 2.0, 5.0, Some(1.0)), (1, 10.0, 15.0, Some(2.0))]);
+
+        assert_eq!(
+            report.to_csv(),
+            "cue,dwell,total,target,over\n\
+             0,2.000,5.000,1.000,1.000\n\
+             1,10.000,15.000,8.000,2.000\n\
+             total,12.000,20.000,9.000,3.000\n"
+        );
+    }
+
+    #[test]
+    fn test_elapsed() {
+        let rehearsal = rehearsal();
+        let mut live = Rehearsal::new(None);
+        live.record(Duration::ZERO, status(State::Held { cue: 3 }, 3));
+
+        assert_eq!(
+            live.elapsed(Duration::from_secs(4)),
+            Some((3, Duration::from_secs(4), None))
+        );
+        assert_eq!(
+            rehearsal.elapsed(Duration::from_secs(30)),
+            Some((1, Duration::from_secs(10), Some(Duration::from_secs(8))))
+        );
+    }
+}
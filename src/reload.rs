@@ -0,0 +1,54 @@
+//! Hot reloading.
+//! Watches the cue files while presenting, so markers can be re-exported without restarting.
+
+use std::{fs, path::Path, thread, time::Duration, time::SystemTime};
+
+use crate::{cues::Cues, error::Error, player::Player, presenter::Presenter, session::Event};
+
+/// How often the cue files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Starts a background thread that reloads the cues of a playlist segment whenever its cue file is modified.
+/// If the new file has errors, they are logged and the old cues are kept.
+pub fn watch<P: Player + 'static>(presenter: &Presenter<P>) {
+    let presenter = presenter.clone();
+    let mut files = presenter
+        .playlist()
+        .segments()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, x)| Some((i, x.markers.clone()?)))
+        .map(|(i, path)| (i, modified(&path), path))
+        .collect::<Vec<_>>();
+
+    thread::spawn(move || loop {
+        thread::sleep(POLL_INTERVAL);
+        for (segment, last, path) in &mut files {
+            let time = modified(path);
+            if time == *last {
+                continue;
+            }
+
+            *last = time;
+            match Cues::from_file(&path) {
+                Ok(cues) => presenter.reload_cues(*segment, cues),
+                Err(e) => {
+                    if let Error::Cues { errors, .. } = &e {
+                        presenter.info(format!(
+                            "Cue file has {} problem(s), keeping the old cues",
+                            errors.len()
+                        ));
+                    }
+                    presenter.log(Event::Error {
+                        message: e.to_string(),
+                    });
+                }
+            }
+        }
+    });
+}
+
+/// Gets when a file was last modified, or `None` if it can't be read (like while it is being re-exported).
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|x| x.modified()).ok()
+}
@@ -0,0 +1,188 @@
+//! Resuming after a crash or restart.
+//! The current cue is saved to a small state file next to the cue file every time it changes, so a new session can jump straight back to it.
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Result,
+    player::Player,
+    presenter::{Config, Presenter},
+    session::Event,
+    state::State,
+};
+
+/// Where a presentation was left off.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resume {
+    /// The video (or playlist) that was playing, so the state of one talk is never applied to another.
+    pub video: PathBuf,
+    pub cue: usize,
+}
+
+impl Resume {
+    /// Path of the state file for a presentation, next to the cue file or playlist.
+    /// (Ex: `talk.csv` is saved to `talk.resume.json`)
+    pub fn path(config: &Config) -> PathBuf {
+        let base = config.playlist.as_ref().unwrap_or(&config.markers);
+        base.with_extension("resume.json")
+    }
+
+    /// Loads the saved state for a presentation, if it was left off part way through the same video.
+    pub fn load(config: &Config) -> Option<Self> {
+        let raw = fs::read_to_string(Self::path(config)).ok()?;
+        let this = serde_json::from_str::<Self>(&raw).ok()?;
+        (this.cue > 0 && this.video == canonical(config.source())).then_some(this)
+    }
+
+    /// Writes the state file, replacing it atomically so a crash mid-write can't corrupt it.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("json.tmp");
+        fs::write(
+            &tmp,
+            serde_json::to_string(self).expect("state is always serializable"),
+        )?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Saves the current cue on a background thread every time it changes.
+    /// Once the end of the video is reached the talk is over, so the state file is removed.
+    pub fn track<P: Player + 'static>(presenter: &Presenter<P>) {
+        let path = Self::path(presenter.config());
+        let video = canonical(presenter.config().source());
+        let statuses = presenter.watch();
+        let presenter = presenter.clone();
+
+        thread::spawn(move || {
+            // The first status is where the session starts, which mustn't replace the state it may be about to resume from
+            let mut last = statuses.recv().ok().map(|x| x.cue);
+            for status in statuses {
+                let res = match status.state {
+                    State::Ended => remove(&path),
+                    _ if last == Some(status.cue) => continue,
+                    _ => Resume {
+                        video: video.clone(),
+                        cue: status.cue,
+                    }
+                    .save(&path),
+                };
+                last = Some(status.cue);
+
+                if let Err(e) = res {
+                    presenter.log(Event::Error {
+                        message: format!("Failed to save resume state: {e}"),
+                    });
+                }
+            }
+        });
+    }
+}
+
+/// Removes the state file, if there is one.
+fn remove(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Makes a path absolute, so the same video is recognized from any working directory.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::Path,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::Resume;
+    use crate::{
+        player::sim::SimPlayer,
+        playlist::Playlist,
+        presenter::{Config, Presenter},
+        state::State,
+    };
+
+    /// Waits for the tracking thread to catch up, until the state file does (or doesn't) exist.
+    fn wait_for(path: &Path, exists: bool) {
+        let start = Instant::now();
+        while path.exists() != exists {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_save_load() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("talk.mp4"), "").unwrap();
+
+        let config = Config {
+            video: dir.join("talk.mp4"),
+            markers: dir.join("talk.csv"),
+            ..Default::default()
+        };
+        assert_eq!(Resume::path(&config), dir.join("talk.resume.json"));
+        assert_eq!(Resume::load(&config), None);
+
+        let resume = Resume {
+            video: fs::canonicalize(&config.video).unwrap(),
+            cue: 4,
+        };
+        resume.save(Resume::path(&config)).unwrap();
+        assert_eq!(Resume::load(&config), Some(resume));
+
+        // A different video with the same cue file is not resumed
+        let other = Config {
+            video: dir.join("other.mp4"),
+            ..config.clone()
+        };
+        assert_eq!(Resume::load(&other), None);
+    }
+
+    #[test]
+    fn test_track_removes_at_end() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config {
+            video: tmp.path().join("talk.mp4"),
+            markers: tmp.path().join("talk.csv"),
+            ..Default::default()
+        };
+        let path = Resume::path(&config);
+
+        let cues = ",,00:00:00:05,00:00:00:05,0,Cue Point\n".parse().unwrap();
+        let playlist = Playlist::single(&config.video, None, cues);
+        let player = SimPlayer::new(100, 20);
+        let presenter = Presenter::with_player(config.clone(), playlist, player).unwrap();
+        let statuses = presenter.watch();
+        Resume::track(&presenter);
+
+        presenter.advance();
+        statuses
+            .iter()
+            .find(|x| x.state == State::Held { cue: 1 })
+            .unwrap();
+        wait_for(&path, true);
+        assert_eq!(Resume::load(&config).map(|x| x.cue), Some(1));
+
+        // Playing to the end doesn't change the cue, but the talk is still over
+        presenter.advance();
+        statuses.iter().find(|x| x.state == State::Ended).unwrap();
+        assert_eq!(presenter.current_cue(), 1);
+        wait_for(&path, false);
+    }
+}
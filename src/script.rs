@@ -0,0 +1,196 @@
+//! Timing scripts, for playing a presentation unattended.
+//!
+//! A script says how long to stay at each cue before advancing, one cue per line as `<cue> <seconds>`.
+//! Blank lines and anything after a `#` are ignored.
+//! ```text
+//! # Intro
+//! 0 2.5
+//! 1 10
+//! 2 4.25
+//! ```
+
+use std::{collections::BTreeMap, fs, path::Path, str::FromStr, thread, time::Duration};
+
+use crate::{
+    error::{Error, Result},
+    player::Player,
+    presenter::Presenter,
+    session::Event,
+    state::{Command, State},
+};
+
+/// How long to wait at each cue before advancing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Script {
+    delays: BTreeMap<usize, Duration>,
+}
+
+impl Script {
+    /// Load a script from a file, see the [module docs](self) for the format.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// How long to wait at a cue before advancing.
+    /// Cues not in the script are advanced past immediately.
+    pub fn delay(&self, cue: usize) -> Duration {
+        self.delays.get(&cue).copied().unwrap_or_default()
+    }
+
+    /// How long to wait at a cue, if it is in the script.
+    pub fn get(&self, cue: usize) -> Option<Duration> {
+        self.delays.get(&cue).copied()
+    }
+
+    /// The sum of the delays at every cue.
+    pub fn total(&self) -> Duration {
+        self.delays.values().sum()
+    }
+
+    /// Plays through the presentation, advancing at each cue after the scripted delay.
+    /// Advancing uses the same command as the space key, so the cue logic is exactly the same as a live talk.
+    /// Returns once the end of the video is reached.
+    pub fn run<P: Player>(&self, presenter: &Presenter<P>) {
+        self.run_with(presenter, thread::sleep);
+    }
+
+    /// Like [`Script::run`], but waiting at each cue with `wait`, so tests don't have to sleep through the delays.
+    fn run_with<P: Player>(&self, presenter: &Presenter<P>, mut wait: impl FnMut(Duration)) {
+        loop {
+            let Some(status) = presenter.status() else {
+                return;
+            };
+
+            let cue = match status.state {
+                State::Idle => 0,
+                State::Held { cue } => cue,
+                State::Ended => return,
+                State::Loading
+                | State::Playing { .. }
+                | State::Rewinding { .. }
+                | State::Blackout { .. } => {
+                    thread::sleep(Duration::from_millis(10));
+                    continue;
+                }
+            };
+
+            let delay = self.get(cue).unwrap_or_else(|| {
+                presenter.log(Event::Warning {
+                    message: format!(
+                        "Cue {cue} is not in the timing script, advancing immediately"
+                    ),
+                });
+                Duration::ZERO
+            });
+
+            println!("[*] At cue {cue}, advancing in {:.2}s", delay.as_secs_f64());
+            wait(delay);
+            presenter.send(Command::Advance);
+        }
+    }
+}
+
+impl FromStr for Script {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut delays = BTreeMap::new();
+
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = |message: String| Error::Script {
+                line: i + 1,
+                message,
+            };
+
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            let [cue, secs] = parts[..] else {
+                return Err(error(format!("expected `<cue> <seconds>`, got `{line}`")));
+            };
+
+            let cue = cue
+                .parse::<usize>()
+                .map_err(|_| error(format!("invalid cue `{cue}`")))?;
+            let secs = secs
+                .parse::<f64>()
+                .ok()
+                .and_then(|x| Duration::try_from_secs_f64(x).ok())
+                .ok_or_else(|| error(format!("invalid number of seconds `{secs}`")))?;
+
+            if delays.insert(cue, secs).is_some() {
+                return Err(error(format!("cue {cue} is listed more than once")));
+            }
+        }
+
+        Ok(Self { delays })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Script;
+    use crate::{
+        player::sim::SimPlayer,
+        playlist::Playlist,
+        presenter::{Config, Presenter},
+        state::State,
+    };
+
+    #[test]
+    fn test_parse_script() {
+        let script = "# intro\n0 1.5\n\n1 2 # slow down here\n2\t0\n"
+            .parse::<Script>()
+            .unwrap();
+
+        assert_eq!(script.delay(0), Duration::from_millis(1500));
+        assert_eq!(script.delay(1), Duration::from_secs(2));
+        assert_eq!(script.delay(2), Duration::ZERO);
+        assert_eq!(script.delay(3), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_parse_script_errors() {
+        for (script, line) in [
+            ("0 1\n1", 2),
+            ("0 1\n\nx 1", 3),
+            ("0 -1", 1),
+            ("0 1\n0 2", 2),
+            ("0 1 2", 1),
+        ] {
+            match script.parse::<Script>() {
+                Err(crate::error::Error::Script { line: l, .. }) => assert_eq!(l, line, "{script}"),
+                x => panic!("expected error for {script:?}, got {x:?}"),
+            }
+        }
+    }
+
+    /// Runs the script against a simulated 100 fps player, recording the (cue, delay, frame) of each wait instead of sleeping.
+    #[test]
+    fn test_run() {
+        // Cue 1 isn't in the script, so it is advanced past straight away
+        let script = "0 0.5\n2 0.2".parse::<Script>().unwrap();
+        let cues = "\
+            ,,00:00:00:10,00:00:00:10,0,Cue Point\n\
+            ,,00:00:00:25,00:00:00:25,0,Cue Point\n";
+        let playlist = Playlist::single("test.mp4", None, cues.parse().unwrap());
+        let player = SimPlayer::new(100, 40);
+        let presenter = Presenter::with_player(Config::default(), playlist, player).unwrap();
+
+        let mut waits = Vec::new();
+        script.run_with(&presenter, |delay| {
+            let status = presenter.status().unwrap();
+            waits.push((status.cue, delay, presenter.player().frame()));
+        });
+
+        let ms = Duration::from_millis;
+        assert_eq!(waits, [(0, ms(500), 0), (1, ms(0), 10), (2, ms(200), 25)]);
+        assert_eq!(presenter.status().unwrap().state, State::Ended);
+        assert_eq!(presenter.player().frame(), 40);
+    }
+}
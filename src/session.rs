@@ -0,0 +1,189 @@
+//! Session log.
+//! Every event in a presentation is appended to a JSON lines file, so there is a record to attach to post-event reports or to work out what happened on stage.
+//! The same events are also printed to the console.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::{error::Result, state::Command};
+
+/// Something that happened during a presentation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The presentation was started.
+    Start {
+        video: PathBuf,
+        markers: PathBuf,
+        playlist: Option<PathBuf>,
+    },
+    /// A cue file was loaded, with the time of each cue.
+    CuesLoaded {
+        file: Option<PathBuf>,
+        cues: Vec<String>,
+    },
+    /// The player finished loading the video, with its metadata.
+    VideoLoaded {
+        name: String,
+        info: Vec<(&'static str, String)>,
+    },
+    /// A command was received, from a key press, the control channel or a script.
+    Command {
+        command: Command,
+    },
+    /// The player was moved to a cue.
+    /// `time` is in seconds within the playlist segment, or `None` for the end of the video.
+    Seek {
+        cue: usize,
+        segment: usize,
+        time: Option<f64>,
+    },
+    /// The current cue changed.
+    Cue {
+        from: usize,
+        to: usize,
+        reached: bool,
+    },
+    /// Playback reached the end of the video.
+    Ended,
+    Warning {
+        message: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// An append-only log of [`Event`]s.
+/// Logging never fails, if the file can't be written to the error is printed and the event is dropped.
+pub struct SessionLog {
+    start: Instant,
+    file: Option<Mutex<File>>,
+}
+
+/// A single line of the log file.
+#[derive(Serialize)]
+struct Line<'a> {
+    /// Seconds since the unix epoch.
+    time: f64,
+    /// Seconds since the log was opened.
+    elapsed: f64,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+impl SessionLog {
+    /// Opens a log file, appending to it if it already exists.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            start: Instant::now(),
+            file: Some(Mutex::new(file)),
+        })
+    }
+
+    /// A log that only prints to the console.
+    pub fn console() -> Self {
+        Self {
+            start: Instant::now(),
+            file: None,
+        }
+    }
+
+    /// Records an event.
+    pub fn log(&self, event: Event) {
+        print(&event);
+
+        let Some(file) = &self.file else {
+            return;
+        };
+
+        let line = Line {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64(),
+            elapsed: self.start.elapsed().as_secs_f64(),
+            event: &event,
+        };
+
+        let mut line = serde_json::to_string(&line).expect("events are always serializable");
+        line.push('\n');
+        if let Err(e) = file.lock().unwrap().write_all(line.as_bytes()) {
+            eprintln!("[-] Failed to write session log: {e}");
+        }
+    }
+}
+
+/// Prints the events worth showing on the console.
+fn print(event: &Event) {
+    match event {
+        Event::CuesLoaded { file, cues } => {
+            match file {
+                Some(file) => {
+                    println!("\n[*] Loaded {} cues from `{}`", cues.len(), file.display())
+                }
+                None => println!("\n[*] Loaded {} cues", cues.len()),
+            }
+            print_tree(cues.iter());
+            println!();
+        }
+        Event::VideoLoaded { name, info } => {
+            println!("[*] Loaded video `{name}`");
+            print_tree(info.iter().map(|(name, val)| format!("{name}: {val}")));
+        }
+        Event::Warning { message } => eprintln!("[WARN] {message}"),
+        Event::Error { message } => eprintln!("[-] {message}"),
+        _ => {}
+    }
+}
+
+fn print_tree<T: std::fmt::Display>(items: impl ExactSizeIterator<Item = T>) {
+    let len = items.len();
+    for (i, e) in items.enumerate() {
+        println!(" {}─ {}", if i + 1 == len { "└" } else { "├" }, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{Event, SessionLog};
+    use crate::state::Command;
+
+    #[test]
+    fn test_log_lines() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("log.jsonl");
+
+        // Opening again appends rather than truncating
+        for event in [
+            Event::Command {
+                command: Command::Goto(3),
+            },
+            Event::Ended,
+        ] {
+            SessionLog::open(&path).unwrap().log(event);
+        }
+
+        let contents = fs::read_to_string(&path).unwrap();
+
+        let lines = contents
+            .lines()
+            .map(|x| serde_json::from_str::<serde_json::Value>(x).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"], "command");
+        assert_eq!(lines[0]["command"], serde_json::json!({ "goto": 3 }));
+        assert_eq!(lines[1]["event"], "ended");
+        assert!(lines[1]["time"].as_f64().unwrap() > 0.0);
+    }
+}
@@ -0,0 +1,1239 @@
+//! The presentation state machine.
+//! All cue state is owned by a single [`Machine`], which is driven by [`Message`]s from the input and player threads.
+//! Because only one thread ever touches the state, a key press and a playback event can never race each other.
+
+use std::{result, str::FromStr, sync::Arc};
+
+use crossbeam::channel::{Receiver, Sender};
+use serde::Serialize;
+
+use crate::{
+    error::{Error, Result},
+    player::{Player, PlayerEvent},
+    playlist::Playlist,
+    presenter::CueEvent,
+    session::{Event, SessionLog},
+    time::{time, Time},
+    transition::Transition,
+};
+
+/// What the presentation is currently doing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum State {
+    /// The player is still loading the video.
+    /// Commands are queued until it has loaded, as seeking needs the fps.
+    Loading,
+    /// Loaded, but playback has not been started yet.
+    Idle,
+    /// Playing, and will pause once cue `target` is reached.
+    Playing { target: usize },
+    /// Paused at (or after) cue `cue`.
+    Held { cue: usize },
+    /// Playing backwards, and will pause once cue `target` is reached.
+    Rewinding { target: usize },
+    /// The end of the video has been reached.
+    Ended,
+    /// The screen is blanked, `prev` is restored when it is un-blanked.
+    Blackout { prev: Box<State> },
+}
+
+/// Actions the presenter can be asked to do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+    /// Continue playing if held, otherwise skip to the next cue.
+    Advance,
+    /// Pause and jump to the next cue.
+    Next,
+    /// Pause and jump to the previous cue.
+    Back,
+    /// Pause and jump to a specific cue.
+    Goto(usize),
+    /// Toggle between paused and playing.
+    TogglePause,
+    /// Step forward one frame.
+    FrameStep,
+    /// Step back one frame.
+    FrameBackStep,
+    /// Blank (or un-blank) the screen.
+    Blackout,
+    /// Play faster, by one step of [`SPEEDS`].
+    Faster,
+    /// Play slower, by one step of [`SPEEDS`].
+    Slower,
+    /// Go back to playing at normal speed.
+    NormalSpeed,
+    /// Show (or hide) the timeline overlay, see [`crate::timeline`].
+    Timeline,
+}
+
+/// The speeds [`Command::Faster`] and [`Command::Slower`] step through.
+pub const SPEEDS: &[f64] = &[0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 2.0, 3.0, 4.0];
+
+impl FromStr for Command {
+    type Err = String;
+
+    /// Parses a command from its name, as used by the headless control channel.
+    /// (Ex: `advance`, `next`, `back`, `goto 3`, `pause`, `step`, `back-step`, `blackout`, `faster`, `slower`, `normal-speed`, `timeline`)
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let cmd = parts.next().unwrap_or_default();
+        let arg = parts.next();
+
+        Ok(match (cmd, arg) {
+            ("advance", None) => Command::Advance,
+            ("next", None) => Command::Next,
+            ("back", None) => Command::Back,
+            ("goto", Some(cue)) => {
+                Command::Goto(cue.parse().map_err(|_| format!("Invalid cue `{cue}`"))?)
+            }
+            ("pause", None) => Command::TogglePause,
+            ("step", None) => Command::FrameStep,
+            ("back-step", None) => Command::FrameBackStep,
+            ("blackout", None) => Command::Blackout,
+            ("faster", None) => Command::Faster,
+            ("slower", None) => Command::Slower,
+            ("normal-speed", None) => Command::NormalSpeed,
+            ("timeline", None) => Command::Timeline,
+            _ => return Err(format!("Unknown command `{}`", s.trim())),
+        })
+    }
+}
+
+/// A snapshot of the machine, see [`crate::presenter::Presenter::status`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Status {
+    pub state: State,
+    /// Index of the current cue.
+    /// 0 is before the first cue and `cues.len() + 1` is the end of the video.
+    pub cue: usize,
+    /// The playlist segment currently playing.
+    pub segment: usize,
+    /// The FPS of the video, or 60 if it is not known yet.
+    pub fps: f64,
+    /// Whether the timeline overlay is shown.
+    pub timeline: bool,
+    /// The playback speed, including the speed of the current section.
+    pub speed: f64,
+}
+
+/// Everything that can be sent to the machine.
+pub enum Message {
+    Command(Command),
+    Player(PlayerEvent),
+    /// Adds a new receiver of cue change events.
+    Subscribe(Sender<CueEvent>),
+    /// Asks for a [`Status`] to be sent back.
+    Status(Sender<Status>),
+    /// Adds a new receiver of the current [`Status`], sent now and then every time the state or cue changes.
+    Watch(Sender<Status>),
+    /// Replaces the cues, after a cue file has been edited.
+    Reload(Arc<Playlist>),
+}
+
+/// Owns the presentation state and applies messages to it.
+pub struct Machine<P: Player> {
+    player: Arc<P>,
+    playlist: Arc<Playlist>,
+    log: Arc<SessionLog>,
+
+    state: State,
+    cue: usize,
+    /// The playlist segment currently loaded in the player.
+    segment: usize,
+    /// Set while switching to another segment, with where to seek to once it has loaded.
+    switching: Option<Time>,
+    fps: Option<f64>,
+    /// Overrides the FPS reported by the player.
+    fixed_fps: Option<f64>,
+    transition: Option<Transition>,
+    /// Whether going back plays backwards to the previous cue, rather than jumping to it.
+    rewind: bool,
+    /// The speed set with [`Command::Faster`] and [`Command::Slower`], multiplied by the speed of the current section.
+    speed: f64,
+    /// Whether the timeline overlay is shown, which replaces the cue number messages.
+    timeline: bool,
+    /// Commands received while loading, applied once loaded.
+    queued: Vec<Command>,
+    subscribers: Vec<Sender<CueEvent>>,
+    watchers: Vec<Sender<Status>>,
+}
+
+impl<P: Player> Machine<P> {
+    /// Creates a machine in the [`State::Loading`] state, with the first segment of the playlist loading.
+    pub fn new(player: Arc<P>, playlist: Arc<Playlist>, log: Arc<SessionLog>) -> Self {
+        Self {
+            player,
+            playlist,
+            log,
+
+            state: State::Loading,
+            cue: 0,
+            segment: 0,
+            switching: None,
+            fps: None,
+            fixed_fps: None,
+            transition: None,
+            rewind: false,
+            speed: 1.0,
+            timeline: false,
+            queued: Vec::new(),
+            subscribers: Vec::new(),
+            watchers: Vec::new(),
+        }
+    }
+
+    /// Uses a fixed frame rate for cue times, instead of the one reported by the player.
+    pub fn with_fps(mut self, fps: Option<f64>) -> Self {
+        self.fixed_fps = fps;
+        self
+    }
+
+    /// Hides the cut with a transition when jumping between cues.
+    /// The machine is blocked while a transition runs, so anything sent meanwhile is handled after it.
+    pub fn with_transition(mut self, transition: Option<Transition>) -> Self {
+        self.transition = transition;
+        self
+    }
+
+    /// Makes going back play backwards to the previous cue, so animations visibly un-build.
+    pub fn with_rewind(mut self, rewind: bool) -> Self {
+        self.rewind = rewind;
+        self
+    }
+
+    /// Handles messages until every sender has been dropped.
+    pub fn run(mut self, messages: Receiver<Message>) {
+        for msg in messages {
+            self.handle(msg);
+        }
+    }
+
+    /// Applies a single message.
+    /// Errors are reported rather than returned, as there is nobody to return them to.
+    pub fn handle(&mut self, msg: Message) {
+        let before = (
+            self.state.clone(),
+            self.cue,
+            self.timeline,
+            self.current_speed(),
+        );
+        let res = match msg {
+            Message::Command(cmd) => self.command(cmd),
+            Message::Player(event) => self.player_event(event),
+            Message::Subscribe(tx) => {
+                self.subscribers.push(tx);
+                Ok(())
+            }
+            Message::Status(tx) => {
+                let _ = tx.send(self.status());
+                Ok(())
+            }
+            Message::Watch(tx) => {
+                if tx.send(self.status()).is_ok() {
+                    self.watchers.push(tx);
+                }
+                Ok(())
+            }
+            Message::Reload(playlist) => self.reload(playlist),
+        };
+
+        if let Err(e) = res {
+            self.report(&e);
+        }
+
+        if before
+            != (
+                self.state.clone(),
+                self.cue,
+                self.timeline,
+                self.current_speed(),
+            )
+        {
+            let status = self.status();
+            self.watchers.retain(|x| x.send(status.clone()).is_ok());
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        Status {
+            state: self.state.clone(),
+            cue: self.cue,
+            segment: self.segment,
+            fps: self.fps(),
+            timeline: self.timeline,
+            speed: self.current_speed(),
+        }
+    }
+
+    fn command(&mut self, cmd: Command) -> Result<()> {
+        if self.state == State::Loading {
+            self.queued.push(cmd);
+            return Ok(());
+        }
+
+        self.log.log(Event::Command {
+            command: cmd.clone(),
+        });
+
+        // This only changes what is drawn over the video, so it never interrupts anything
+        if cmd == Command::Timeline {
+            self.timeline = !self.timeline;
+            return Ok(());
+        }
+
+        if let State::Blackout { prev } = &self.state {
+            // Only un-blanking is allowed while blanked, so nothing changes behind the black screen
+            if cmd == Command::Blackout {
+                self.state = *prev.clone();
+                self.player.set_blackout(false)?;
+            }
+            return Ok(());
+        }
+
+        // Going back again while rewinding carries on to the cue before, anything else stops where it is first
+        if let State::Rewinding { target } = self.state {
+            match cmd {
+                Command::Back => return self.back(target),
+                Command::TogglePause => return self.stop_rewind(),
+                _ => self.stop_rewind()?,
+            }
+        }
+
+        match cmd {
+            Command::Advance => match self.state {
+                State::Idle | State::Held { .. } => self.play()?,
+                State::Playing { target } => {
+                    // Skip ahead to the cue we were playing towards, and keep going
+                    self.seek_to(target)?;
+                    if target <= self.playlist.len() {
+                        self.state = State::Playing { target: target + 1 };
+                    }
+                }
+                State::Loading
+                | State::Ended
+                | State::Rewinding { .. }
+                | State::Blackout { .. } => {}
+            },
+            Command::Next => {
+                self.jump((self.cue + 1).min(self.playlist.len() + 1))?;
+                self.show_cue();
+            }
+            Command::Back => self.back(self.cue)?,
+            Command::Goto(cue) => self.jump(cue.min(self.playlist.len() + 1))?,
+            Command::TogglePause => match self.state {
+                State::Idle | State::Held { .. } => self.play()?,
+                State::Playing { .. } => self.hold()?,
+                State::Loading
+                | State::Ended
+                | State::Rewinding { .. }
+                | State::Blackout { .. } => {}
+            },
+            Command::FrameStep => {
+                self.player.frame_step()?;
+                self.auto_cue()?;
+            }
+            Command::FrameBackStep => {
+                self.player.frame_back_step()?;
+                self.auto_cue()?;
+            }
+            Command::Faster | Command::Slower | Command::NormalSpeed => {
+                self.speed = match cmd {
+                    Command::Faster => SPEEDS.iter().find(|x| **x > self.speed),
+                    Command::Slower => SPEEDS.iter().rfind(|x| **x < self.speed),
+                    _ => Some(&1.0),
+                }
+                .copied()
+                .unwrap_or(self.speed);
+
+                self.apply_speed()?;
+                self.info(format!("Speed {}×", self.speed));
+            }
+            // Handled before anything else
+            Command::Timeline => {}
+            Command::Blackout => {
+                self.player.pause()?;
+                self.player.set_blackout(true)?;
+
+                // Playback is paused while blanked, so resume from the held state
+                let prev = match self.state {
+                    State::Playing { .. } => State::Held { cue: self.cue },
+                    ref x => x.clone(),
+                };
+                self.state = State::Blackout {
+                    prev: Box::new(prev),
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    fn player_event(&mut self, event: PlayerEvent) -> Result<()> {
+        match event {
+            // If the file has loaded, get its FPS and log some info
+            PlayerEvent::Loaded { fps, info } => {
+                self.fps = fps.or(self.fps);
+                let media = &self.playlist.segments()[self.segment].media;
+                self.log.log(Event::VideoLoaded {
+                    name: media
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                    info,
+                });
+
+                // Finish switching segments, carrying on playing if we were before
+                if let Some(time) = self.switching.take() {
+                    self.seek(time)?;
+                    if let State::Playing { target } = self.state {
+                        // A cue right at the start (like a still image) is reached straight away
+                        let time = time.as_secs(self.fps());
+                        if self.playlist.current(self.segment, time, self.fps()) >= target {
+                            self.reach(target, time)?;
+                        } else if self.playlist.segments()[self.segment].still {
+                            self.next_segment()?;
+                        } else {
+                            self.player.unpause()?;
+                        }
+                    }
+                }
+
+                if self.state == State::Loading {
+                    self.state = State::Idle;
+                    for cmd in std::mem::take(&mut self.queued) {
+                        self.command(cmd)?;
+                    }
+                }
+            }
+            // Anything from the old segment is stale while switching
+            PlayerEvent::Position(_) | PlayerEvent::Ended if self.switching.is_some() => {}
+            // If the playback-time has changed, check if we need to pause
+            PlayerEvent::Position(time) => match self.state {
+                State::Playing { target }
+                    if self.playlist.current(self.segment, time, self.fps()) >= target =>
+                {
+                    self.reach(target, time)?;
+                }
+                // Stop within half a frame of the cue, the seek when finishing lands on it exactly
+                State::Rewinding { target } => {
+                    let at = self.playlist.get(target).1.as_secs(self.fps());
+                    if time <= at + 0.5 / self.fps() {
+                        self.finish_rewind(target)?;
+                    }
+                }
+                _ => {}
+            },
+            // Rewinding all the way back to the start of the file is reported as an end too
+            PlayerEvent::Ended if matches!(self.state, State::Rewinding { .. }) => {
+                if let State::Rewinding { target } = self.state {
+                    self.finish_rewind(target)?;
+                }
+            }
+            // Playing past the end of a segment flows into the next one
+            PlayerEvent::Ended if self.segment + 1 < self.playlist.segments().len() => {
+                if let State::Playing { .. } = self.state {
+                    self.next_segment()?;
+                }
+            }
+            // Only playing can reach the end, otherwise it was reported before a seek (like back to a cue that was passed)
+            PlayerEvent::Ended => {
+                if let State::Playing { .. } = self.state {
+                    self.state = State::Ended;
+                    self.log.log(Event::Ended);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Swaps in a new set of cues, working out the current cue again from the playback position.
+    /// Playback carries on as before, towards the cue after the new current one.
+    fn reload(&mut self, playlist: Arc<Playlist>) -> Result<()> {
+        let (mut added, mut removed) = (0, 0);
+        for (old, new) in self.playlist.segments().iter().zip(playlist.segments()) {
+            added += new.cues.iter().filter(|x| !old.cues.contains(x)).count();
+            removed += old.cues.iter().filter(|x| !new.cues.contains(x)).count();
+        }
+        self.playlist = playlist;
+        self.info(format!("Reloaded cues (+{added} -{removed})"));
+
+        if matches!(self.state, State::Loading | State::Ended) || self.switching.is_some() {
+            return Ok(());
+        }
+
+        let time = self.player.position()?;
+        let cue = self.playlist.current(self.segment, time, self.fps());
+        self.set_cue(cue, false);
+
+        let state = match &mut self.state {
+            State::Blackout { prev } => prev.as_mut(),
+            x => x,
+        };
+        match state {
+            State::Playing { target } => *target = cue + 1,
+            State::Held { cue: held } => *held = cue,
+            // The old target may be a different cue now, so stop at the start of the section instead
+            State::Rewinding { target } => *target = cue,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    // == Helpers ==
+
+    /// Holds on the cue being played towards, once playback has reached or passed it.
+    /// Positions are only reported every so often (less often than cues at high speed or during a lag spike),
+    /// so if several cues were crossed since the last one, playback goes back to the first rather than skipping any.
+    fn reach(&mut self, target: usize, time: f64) -> Result<()> {
+        self.player.pause()?;
+        self.set_cue(target, true);
+        self.state = State::Held { cue: target };
+
+        let (segment, at) = self.playlist.get(target);
+        if segment == self.segment && time > at.as_secs(self.fps()) + 0.5 / self.fps() {
+            self.seek(at)?;
+        }
+        Ok(())
+    }
+
+    /// Starts playing towards the next cue.
+    /// Still images never end, so playing from one moves straight on to the next segment.
+    fn play(&mut self) -> Result<()> {
+        self.state = State::Playing {
+            target: self.cue + 1,
+        };
+        match self.playlist.segments()[self.segment].still {
+            true => self.next_segment(),
+            false => self.player.unpause(),
+        }
+    }
+
+    /// Moves on to the start of the next segment while playing, or ends if this is the last one.
+    fn next_segment(&mut self) -> Result<()> {
+        if self.segment + 1 < self.playlist.segments().len() {
+            return self.switch(self.segment + 1, time!(00:00:00:00));
+        }
+
+        self.state = State::Ended;
+        self.log.log(Event::Ended);
+        Ok(())
+    }
+
+    /// Pauses wherever playback currently is.
+    fn hold(&mut self) -> Result<()> {
+        self.player.pause()?;
+        if self.state != State::Ended {
+            self.state = State::Held { cue: self.cue };
+        }
+        Ok(())
+    }
+
+    /// Goes back to the cue before `from`.
+    /// With rewinding on this plays backwards to it, unless it is in another segment.
+    fn back(&mut self, from: usize) -> Result<()> {
+        let cue = from.saturating_sub(1);
+        let in_segment = self.playlist.get(cue).0 == self.segment && self.switching.is_none();
+        if self.rewind && in_segment {
+            return self.rewind_to(cue);
+        }
+
+        if let State::Rewinding { .. } = self.state {
+            self.stop_rewind()?;
+        }
+        self.jump(cue)?;
+        self.show_cue();
+        Ok(())
+    }
+
+    /// Starts playing backwards towards an earlier cue in the current segment.
+    fn rewind_to(&mut self, cue: usize) -> Result<()> {
+        self.player.set_reverse(true)?;
+        self.player.unpause()?;
+        self.state = State::Rewinding { target: cue };
+        Ok(())
+    }
+
+    /// Pauses on the cue that was being rewound to, ready to play forwards again.
+    fn finish_rewind(&mut self, cue: usize) -> Result<()> {
+        self.player.pause()?;
+        self.player.set_reverse(false)?;
+        self.seek(self.playlist.get(cue).1)?;
+        self.set_cue(cue, true);
+        self.state = State::Held { cue };
+        self.show_cue();
+        Ok(())
+    }
+
+    /// Stops rewinding part way, holding wherever playback got to.
+    fn stop_rewind(&mut self) -> Result<()> {
+        self.player.pause()?;
+        self.player.set_reverse(false)?;
+        self.auto_cue()
+    }
+
+    /// Pauses and seeks to a cue, behind the transition if there is one.
+    fn jump(&mut self, cue: usize) -> Result<()> {
+        self.hold()?;
+        match self.transition {
+            Some(transition) if cue != self.cue => {
+                let player = self.player.clone();
+                transition.run(&*player, || self.seek_to(cue))
+            }
+            _ => self.seek_to(cue),
+        }
+    }
+
+    /// Seeks to a cue, holding there unless playing.
+    /// Anything past the last cue is the end of the video.
+    fn seek_to(&mut self, cue: usize) -> Result<()> {
+        let (segment, time) = self.playlist.get(cue);
+        self.set_cue(cue, false);
+        self.log.log(Event::Seek {
+            cue,
+            segment,
+            time: (!time.is_end()).then(|| time.as_secs(self.fps())),
+        });
+
+        if time.is_end() {
+            self.state = State::Ended;
+        } else if !matches!(self.state, State::Playing { .. }) {
+            self.state = State::Held { cue };
+        }
+
+        match segment == self.segment && self.switching.is_none() {
+            true => self.seek(time),
+            false => self.switch(segment, time),
+        }
+    }
+
+    /// Seeks within the current segment.
+    fn seek(&self, time: Time) -> Result<()> {
+        match time.is_end() {
+            true => self.player.seek_end(),
+            false => self.player.seek(time.as_secs(self.fps())),
+        }
+    }
+
+    /// Starts loading another segment, seeking to `time` once it has loaded.
+    fn switch(&mut self, segment: usize, time: Time) -> Result<()> {
+        self.segment = segment;
+        self.switching = Some(time);
+        self.player.switch(segment)
+    }
+
+    /// Automatically update the current cue point based on the playback time
+    /// This is not always accurate, because mpv doesn't supply a frame number but a value in seconds.
+    /// So rounding errors and such can cause one cue point to be interpreted as another.
+    fn auto_cue(&mut self) -> Result<()> {
+        let time = self.player.position()?;
+        let cue = self.playlist.current(self.segment, time, self.fps());
+        self.set_cue(cue, false);
+        self.state = State::Held { cue };
+        Ok(())
+    }
+
+    /// Updates the current cue and notifies any subscribers.
+    fn set_cue(&mut self, cue: usize, reached: bool) {
+        let from = self.cue;
+        if from == cue {
+            return;
+        }
+
+        self.cue = cue;
+        let event = CueEvent {
+            from,
+            to: cue,
+            reached,
+        };
+        self.subscribers.retain(|x| x.send(event).is_ok());
+        self.log.log(Event::Cue {
+            from,
+            to: cue,
+            reached,
+        });
+
+        if let Err(e) = self.apply_speed() {
+            self.report(&e);
+        }
+    }
+
+    /// Sets the player's speed for the current section.
+    fn apply_speed(&self) -> Result<()> {
+        self.player.set_speed(self.current_speed())
+    }
+
+    /// The speed set with the keys, multiplied by the speed of the current section.
+    fn current_speed(&self) -> f64 {
+        self.speed * self.playlist.speed(self.cue).unwrap_or(1.0)
+    }
+
+    /// Display a message on the screen using the player's OSD.
+    /// Failing to show a message is not worth interrupting anything over, so errors are only logged.
+    fn info(&self, msg: impl AsRef<str>) {
+        if let Err(e) = self.player.show_text(msg.as_ref()) {
+            self.log.log(Event::Error {
+                message: e.to_string(),
+            });
+        }
+    }
+
+    /// Shows the number of the current cue, unless the timeline is already showing it.
+    fn show_cue(&self) {
+        if !self.timeline {
+            self.info(format!("#{}", self.cue));
+        }
+    }
+
+    /// Logs an error and shows it on the OSD.
+    fn report(&self, err: &Error) {
+        self.log.log(Event::Error {
+            message: err.to_string(),
+        });
+        self.info(format!("Error: {err}"));
+    }
+
+    fn fps(&self) -> f64 {
+        self.fixed_fps.or(self.fps).unwrap_or(60.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crossbeam::channel;
+
+    use super::{Command, Machine, Message, State};
+    use crate::{
+        cues::Cues,
+        player::{sim::SimPlayer, Player, PlayerEvent},
+        playlist::{Playlist, Segment},
+        presenter::CueEvent,
+        session::SessionLog,
+    };
+
+    const CUES: &str = "\
+        ,,00:00:01:00,00:00:01:00,0,Cue Point\n\
+        ,,00:00:02:05,00:00:02:05,0,Cue Point\n\
+        ,,00:00:03:00,00:00:03:00,0,Cue Point\n";
+
+    /// A 10 fps, 5 second video with cues on frames 10, 25 and 30.
+    fn machine() -> Machine<SimPlayer> {
+        let mut machine = Machine::new(
+            Arc::new(SimPlayer::new(10, 50)),
+            Arc::new(Playlist::single("test.mp4", None, CUES.parse().unwrap())),
+            Arc::new(SessionLog::console()),
+        );
+        run(&mut machine, 1);
+        machine
+    }
+
+    /// Steps the simulated player one frame at a time, passing all events to the machine.
+    fn run(machine: &mut Machine<SimPlayer>, frames: u64) {
+        for _ in 0..frames {
+            for event in machine.player.step(1) {
+                machine.handle(Message::Player(event));
+            }
+        }
+    }
+
+    fn send(machine: &mut Machine<SimPlayer>, cmd: Command) {
+        machine.handle(Message::Command(cmd));
+    }
+
+    #[test]
+    fn test_play_stops_at_cues() {
+        let mut machine = machine();
+        let (tx, events) = channel::unbounded();
+        machine.handle(Message::Subscribe(tx));
+        assert_eq!(machine.state, State::Idle);
+
+        for (cue, frame) in [(1, 10), (2, 25), (3, 30)] {
+            send(&mut machine, Command::Advance);
+            assert_eq!(machine.state, State::Playing { target: cue });
+            run(&mut machine, 100);
+
+            assert!(machine.player.is_paused().unwrap());
+            assert_eq!(machine.player.frame(), frame);
+            assert_eq!(machine.state, State::Held { cue });
+            assert_eq!(
+                events.try_recv().unwrap(),
+                CueEvent {
+                    from: cue - 1,
+                    to: cue,
+                    reached: true
+                }
+            );
+        }
+
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 100);
+        assert_eq!(machine.player.frame(), 50);
+        assert_eq!(machine.state, State::Ended);
+        assert_eq!(machine.cue, 3);
+    }
+
+    #[test]
+    fn test_advance_while_playing_skips_to_cue() {
+        let mut machine = machine();
+
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 3);
+        assert!(!machine.player.is_paused().unwrap());
+
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 1);
+        assert_eq!(machine.cue, 1);
+        assert_eq!(machine.player.frame(), 11);
+
+        // Keeps playing on to the next cue from there
+        run(&mut machine, 100);
+        assert_eq!(machine.state, State::Held { cue: 2 });
+        assert_eq!(machine.player.frame(), 25);
+    }
+
+    #[test]
+    fn test_next_and_back() {
+        let mut machine = machine();
+
+        send(&mut machine, Command::Next);
+        send(&mut machine, Command::Next);
+        run(&mut machine, 1);
+        assert_eq!(machine.state, State::Held { cue: 2 });
+        assert_eq!(machine.player.frame(), 25);
+
+        send(&mut machine, Command::Back);
+        run(&mut machine, 1);
+        assert_eq!(machine.state, State::Held { cue: 1 });
+        assert_eq!(machine.player.frame(), 10);
+        assert_eq!(machine.player.messages(), ["#1", "#2", "#1"]);
+
+        // Going past the last cue goes to the end, and no further
+        for _ in 0..10 {
+            send(&mut machine, Command::Next);
+        }
+        run(&mut machine, 1);
+        assert_eq!(machine.state, State::Ended);
+        assert_eq!(machine.cue, 4);
+        assert_eq!(machine.player.frame(), 50);
+
+        // Going before the start stays at the start
+        for _ in 0..10 {
+            send(&mut machine, Command::Back);
+        }
+        run(&mut machine, 1);
+        assert_eq!(machine.state, State::Held { cue: 0 });
+        assert_eq!(machine.player.frame(), 0);
+        assert!(machine.player.is_paused().unwrap());
+    }
+
+    #[test]
+    fn test_next_while_playing_does_not_race() {
+        let mut machine = machine();
+
+        // A position event from before the key press arrives after it
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 9);
+        send(&mut machine, Command::Next);
+        assert_eq!(machine.state, State::Held { cue: 1 });
+        machine.handle(Message::Player(PlayerEvent::Position(0.9)));
+
+        assert_eq!(machine.state, State::Held { cue: 1 });
+        assert_eq!(machine.cue, 1);
+        assert_eq!(machine.player.frame(), 10);
+    }
+
+    #[test]
+    fn test_goto() {
+        let mut machine = machine();
+
+        send(&mut machine, Command::Goto(3));
+        run(&mut machine, 1);
+        assert_eq!(machine.state, State::Held { cue: 3 });
+        assert_eq!(machine.player.frame(), 30);
+
+        send(&mut machine, Command::Goto(100));
+        run(&mut machine, 1);
+        assert_eq!(machine.state, State::Ended);
+        assert_eq!(machine.cue, 4);
+        assert_eq!(machine.player.frame(), 50);
+    }
+
+    #[test]
+    fn test_commands_wait_for_load() {
+        let mut machine = Machine::new(
+            Arc::new(SimPlayer::new(10, 50)),
+            Arc::new(Playlist::single("test.mp4", None, CUES.parse().unwrap())),
+            Arc::new(SessionLog::console()),
+        );
+
+        // Like resuming on startup, before the player has loaded the video
+        send(&mut machine, Command::Goto(2));
+        assert_eq!(machine.state, State::Loading);
+
+        run(&mut machine, 2);
+        assert_eq!(machine.state, State::Held { cue: 2 });
+        assert_eq!(machine.player.frame(), 25);
+    }
+
+    /// Two 10 fps videos, 3s with a cue at 1s and 2s with a cue at 0.5s.
+    #[test]
+    fn test_playlist() {
+        let segment = |media: &str, cues: &str| Segment {
+            media: media.into(),
+            markers: None,
+            cues: cues.parse().unwrap(),
+            still: false,
+        };
+        let playlist = Playlist::from_segments(vec![
+            segment("a.mp4", ",,00:00:01:00,00:00:01:00,0,Cue Point\n"),
+            segment("b.mp4", ",,00:00:00:05,00:00:00:05,0,Cue Point\n"),
+        ]);
+
+        let player = Arc::new(SimPlayer::playlist(10, vec![30, 20]));
+        let mut machine = Machine::new(
+            player.clone(),
+            Arc::new(playlist),
+            Arc::new(SessionLog::console()),
+        );
+        run(&mut machine, 1);
+
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 100);
+        assert_eq!((player.segment(), player.frame()), (0, 10));
+
+        // Playing past the end of the first video continues into the second
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 100);
+        assert_eq!(machine.state, State::Held { cue: 2 });
+        assert_eq!((player.segment(), player.frame()), (1, 5));
+
+        // Going back from the first cue of a video goes to the last cue of the one before
+        send(&mut machine, Command::Back);
+        run(&mut machine, 2);
+        assert_eq!(machine.state, State::Held { cue: 1 });
+        assert_eq!((player.segment(), player.frame()), (0, 10));
+
+        send(&mut machine, Command::Next);
+        run(&mut machine, 2);
+        assert_eq!(machine.state, State::Held { cue: 2 });
+        assert_eq!((player.segment(), player.frame()), (1, 5));
+
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 100);
+        assert_eq!(machine.state, State::Ended);
+        assert_eq!((player.segment(), player.frame()), (1, 20));
+    }
+
+    #[test]
+    fn test_stills() {
+        let still = |media: &str| Segment {
+            media: media.into(),
+            markers: None,
+            cues: Cues::still(),
+            still: true,
+        };
+        let playlist = Playlist::from_segments(vec![
+            Segment {
+                media: "a.mp4".into(),
+                markers: None,
+                cues: Cues::default(),
+                still: false,
+            },
+            still("b.png"),
+            still("c.png"),
+        ]);
+
+        let player = Arc::new(SimPlayer::playlist(10, vec![20, u64::MAX, u64::MAX]));
+        let mut machine = Machine::new(
+            player.clone(),
+            Arc::new(playlist),
+            Arc::new(SessionLog::console()),
+        );
+        run(&mut machine, 1);
+
+        // Playing into an image holds on it
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 100);
+        assert_eq!(machine.state, State::Held { cue: 1 });
+        assert_eq!(player.segment(), 1);
+
+        // Advancing from an image moves straight on to the next one
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 5);
+        assert_eq!(machine.state, State::Held { cue: 2 });
+        assert_eq!(player.segment(), 2);
+
+        send(&mut machine, Command::Back);
+        run(&mut machine, 2);
+        assert_eq!(machine.state, State::Held { cue: 1 });
+        assert_eq!(player.segment(), 1);
+
+        send(&mut machine, Command::Goto(2));
+        run(&mut machine, 2);
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 2);
+        assert_eq!(machine.state, State::Ended);
+    }
+
+    #[test]
+    fn test_sparse_positions() {
+        let mut machine = machine();
+        let (tx, events) = channel::unbounded();
+        machine.handle(Message::Subscribe(tx));
+
+        // Each step is one position update, the first crossing all three cues (frames 10, 25 and 30) at once
+        let mut held = Vec::new();
+        for frames in [40, 20, 40, 40] {
+            send(&mut machine, Command::Advance);
+            for event in machine.player.step(frames) {
+                machine.handle(Message::Player(event));
+            }
+            run(&mut machine, 1);
+            held.push((machine.state.clone(), machine.player.frame()));
+        }
+
+        assert_eq!(
+            held,
+            [
+                (State::Held { cue: 1 }, 10),
+                (State::Held { cue: 2 }, 25),
+                (State::Held { cue: 3 }, 30),
+                (State::Ended, 50)
+            ]
+        );
+        assert!(events.try_iter().all(|x| x.to == x.from + 1 && x.reached));
+    }
+
+    #[test]
+    fn test_speed() {
+        let cues = "\
+            ,speed=4,00:00:01:00,00:00:01:00,0,Cue Point\n\
+            ,,00:00:02:09,00:00:02:09,0,Cue Point\n";
+        let mut machine = Machine::new(
+            Arc::new(SimPlayer::new(10, 50)),
+            Arc::new(Playlist::single("test.mp4", None, cues.parse().unwrap())),
+            Arc::new(SessionLog::console()),
+        );
+        run(&mut machine, 1);
+
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 100);
+        assert_eq!(machine.state, State::Held { cue: 1 });
+        assert_eq!(machine.player.speed(), 4.0);
+
+        // Four frames a step jumps from frame 26 to 30, but it still stops on the cue's frame
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 100);
+        assert_eq!(machine.state, State::Held { cue: 2 });
+        assert_eq!(machine.player.frame(), 29);
+        assert_eq!(machine.player.speed(), 1.0);
+
+        // The speed keys multiply the speed of the section
+        send(&mut machine, Command::Faster);
+        send(&mut machine, Command::Faster);
+        assert_eq!(machine.player.speed(), 1.5);
+        send(&mut machine, Command::Back);
+        assert_eq!(machine.player.speed(), 6.0);
+        assert_eq!(machine.status().speed, 6.0);
+        send(&mut machine, Command::NormalSpeed);
+        assert_eq!(machine.player.speed(), 4.0);
+    }
+
+    #[test]
+    fn test_rewind() {
+        let mut machine = machine().with_rewind(true);
+        send(&mut machine, Command::Goto(3));
+        run(&mut machine, 1);
+        assert_eq!(machine.player.frame(), 30);
+
+        // Plays backwards through the animation, only changing cue once the earlier one is reached
+        send(&mut machine, Command::Back);
+        assert_eq!(machine.state, State::Rewinding { target: 2 });
+        run(&mut machine, 3);
+        assert_eq!(machine.cue, 3);
+        assert_eq!(machine.player.frame(), 27);
+        run(&mut machine, 2);
+        assert_eq!(machine.state, State::Held { cue: 2 });
+        assert_eq!(machine.cue, 2);
+        assert_eq!(machine.player.frame(), 25);
+        assert!(!machine.player.reverse());
+
+        // Going back again part way carries on to the cue before
+        send(&mut machine, Command::Back);
+        run(&mut machine, 5);
+        send(&mut machine, Command::Back);
+        assert_eq!(machine.state, State::Rewinding { target: 0 });
+        run(&mut machine, 100);
+        assert_eq!(machine.state, State::Held { cue: 0 });
+        assert_eq!(machine.player.frame(), 0);
+
+        // Anything else stops where it is
+        send(&mut machine, Command::Goto(2));
+        run(&mut machine, 1);
+        send(&mut machine, Command::Back);
+        run(&mut machine, 7);
+        send(&mut machine, Command::TogglePause);
+        assert_eq!(machine.state, State::Held { cue: 1 });
+        assert_eq!(machine.player.frame(), 18);
+        assert!(!machine.player.reverse());
+    }
+
+    #[test]
+    fn test_reload() {
+        let mut machine = machine();
+        send(&mut machine, Command::Goto(2));
+        run(&mut machine, 1);
+        assert_eq!(machine.player.frame(), 25);
+
+        // Frame 25 is now between the first (frame 20) and second (frame 40) cue
+        let cues = "\
+            ,,00:00:02:00,00:00:02:00,0,Cue Point\n\
+            ,,00:00:04:00,00:00:04:00,0,Cue Point\n";
+        let playlist = machine.playlist.with_cues(0, cues.parse().unwrap());
+        machine.handle(Message::Reload(Arc::new(playlist)));
+        assert_eq!(machine.state, State::Held { cue: 1 });
+        assert_eq!(machine.cue, 1);
+        assert_eq!(
+            machine.player.messages().last().unwrap(),
+            "Reloaded cues (+2 -3)"
+        );
+
+        send(&mut machine, Command::Advance);
+        assert_eq!(machine.state, State::Playing { target: 2 });
+        run(&mut machine, 100);
+        assert_eq!(machine.state, State::Held { cue: 2 });
+        assert_eq!(machine.player.frame(), 40);
+    }
+
+    #[test]
+    fn test_reload_while_rewinding() {
+        let mut machine = machine().with_rewind(true);
+        send(&mut machine, Command::Goto(3));
+        run(&mut machine, 1);
+        send(&mut machine, Command::Back);
+        run(&mut machine, 3);
+        assert_eq!(machine.state, State::Rewinding { target: 2 });
+        assert_eq!(machine.player.frame(), 27);
+
+        // Frame 27 is now after the first cue (frame 20), so that is where the rewind stops
+        let cues = "\
+            ,,00:00:02:00,00:00:02:00,0,Cue Point\n\
+            ,,00:00:04:00,00:00:04:00,0,Cue Point\n";
+        let playlist = machine.playlist.with_cues(0, cues.parse().unwrap());
+        machine.handle(Message::Reload(Arc::new(playlist)));
+        assert_eq!(machine.state, State::Rewinding { target: 1 });
+
+        run(&mut machine, 100);
+        assert_eq!(machine.state, State::Held { cue: 1 });
+        assert_eq!(machine.cue, 1);
+        assert_eq!(machine.player.frame(), 20);
+        assert!(!machine.player.reverse());
+    }
+
+    #[test]
+    fn test_frame_step() {
+        let mut machine = machine();
+
+        send(&mut machine, Command::Goto(1));
+        send(&mut machine, Command::FrameBackStep);
+        assert_eq!(machine.state, State::Held { cue: 0 });
+        assert_eq!(machine.player.frame(), 9);
+
+        send(&mut machine, Command::FrameStep);
+        assert_eq!(machine.state, State::Held { cue: 1 });
+
+        // Stepping across a cue does not need another advance
+        send(&mut machine, Command::Goto(2));
+        for _ in 0..5 {
+            send(&mut machine, Command::FrameStep);
+        }
+        assert_eq!(machine.state, State::Held { cue: 3 });
+        assert_eq!(machine.player.frame(), 30);
+
+        // Playing on from a stepped cue stops at the end
+        run(&mut machine, 1);
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 100);
+        assert_eq!(machine.state, State::Ended);
+        assert_eq!(machine.player.frame(), 50);
+        assert!(machine.player.is_paused().unwrap());
+    }
+
+    #[test]
+    fn test_toggle_pause() {
+        let mut machine = machine();
+
+        send(&mut machine, Command::TogglePause);
+        run(&mut machine, 5);
+        send(&mut machine, Command::TogglePause);
+        run(&mut machine, 5);
+        assert_eq!(machine.player.frame(), 5);
+        assert_eq!(machine.state, State::Held { cue: 0 });
+
+        // Resuming still stops at the next cue
+        send(&mut machine, Command::TogglePause);
+        run(&mut machine, 100);
+        assert_eq!(machine.state, State::Held { cue: 1 });
+        assert_eq!(machine.player.frame(), 10);
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!("advance".parse(), Ok(Command::Advance));
+        assert_eq!(" goto  3 ".parse(), Ok(Command::Goto(3)));
+        assert_eq!("back-step".parse(), Ok(Command::FrameBackStep));
+        assert!("goto".parse::<Command>().is_err());
+        assert!("goto x".parse::<Command>().is_err());
+        assert!("next 2".parse::<Command>().is_err());
+        assert!("fly".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_timeline() {
+        let mut machine = machine();
+        send(&mut machine, Command::Next);
+        assert_eq!(machine.player.messages(), ["#1"]);
+
+        // The timeline shows the cue instead, and toggling it doesn't interrupt playback
+        send(&mut machine, Command::Advance);
+        send(&mut machine, Command::Timeline);
+        assert!(machine.status().timeline);
+        assert_eq!(machine.state, State::Playing { target: 2 });
+
+        send(&mut machine, Command::Next);
+        assert_eq!(machine.player.messages(), ["#1"]);
+
+        send(&mut machine, Command::Timeline);
+        send(&mut machine, Command::Next);
+        assert_eq!(machine.player.messages(), ["#1", "#3"]);
+    }
+
+    #[test]
+    fn test_blackout() {
+        let mut machine = machine();
+
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 5);
+        send(&mut machine, Command::Blackout);
+        assert!(machine.player.blackout());
+        assert!(machine.player.is_paused().unwrap());
+
+        // Navigation is ignored while blanked
+        send(&mut machine, Command::Next);
+        run(&mut machine, 5);
+        assert_eq!(machine.player.frame(), 5);
+
+        send(&mut machine, Command::Blackout);
+        assert!(!machine.player.blackout());
+        assert_eq!(machine.state, State::Held { cue: 0 });
+
+        send(&mut machine, Command::Advance);
+        run(&mut machine, 100);
+        assert_eq!(machine.state, State::Held { cue: 1 });
+    }
+}
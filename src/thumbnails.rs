@@ -0,0 +1,235 @@
+//! Thumbnails of every cue, for the [overview](crate::overview).
+//! Grabbing the frames takes a while, so they are cached on disk and only made once for each video.
+//! The cache is keyed by a hash of the media rather than its path, so moving a video keeps its thumbnails and re-exporting it makes new ones.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    thread,
+};
+
+use crate::{
+    error::Result,
+    player::{mpv::MpvPlayer, Player},
+    playlist::Playlist,
+    presenter::Presenter,
+    session::Event,
+    time::Time,
+};
+
+/// Width the frames are scaled down to.
+const WIDTH: u32 = 480;
+/// How much of the start and end of a file is hashed.
+const SAMPLE: u64 = 1 << 20;
+
+/// Gets the thumbnail of every cue in the playlist, from cue 0 to the last one.
+/// These are only paths, the files may not have been made yet (see [`generate`]).
+/// A still image is its own thumbnail.
+pub fn paths(playlist: &Playlist) -> Result<Vec<PathBuf>> {
+    let dirs = playlist
+        .segments()
+        .iter()
+        .map(|x| match x.still {
+            true => Ok(None),
+            false => media_dir(&x.media).map(Some),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok((0..=playlist.len())
+        .map(|cue| {
+            let (segment, time) = playlist.get(cue);
+            match &dirs[segment] {
+                Some(dir) => dir.join(file_name(time)),
+                None => playlist.segments()[segment].media.clone(),
+            }
+        })
+        .collect())
+}
+
+/// Makes any thumbnails of the playlist that aren't already cached, calling `ready` each time a file's thumbnails have been saved.
+/// `fps` overrides the frame rate reported by the videos, like [`crate::presenter::Config::fps`].
+/// A file that fails doesn't stop the rest from being made, the first error is returned at the end.
+pub fn generate(playlist: &Playlist, fps: Option<f64>, mut ready: impl FnMut()) -> Result<()> {
+    let mut res = Ok(());
+    for (segment, frames) in missing(playlist)? {
+        let media = &playlist.segments()[segment].media;
+        let saved = save(media, fps, &frames).map(|()| ready());
+        res = res.and(saved);
+    }
+
+    res
+}
+
+/// Makes any missing thumbnails on a background thread, calling `ready` whenever more are available.
+pub fn start<P: Player + 'static>(presenter: &Presenter<P>, ready: impl FnMut() + Send + 'static) {
+    let presenter = presenter.clone();
+    thread::spawn(move || {
+        if let Err(e) = generate(&presenter.playlist(), presenter.config().fps, ready) {
+            presenter.log(Event::Error {
+                message: format!("Failed to make thumbnails: {e}"),
+            });
+        }
+    });
+}
+
+/// Gets the thumbnails that haven't been cached yet, grouped by segment.
+/// Cues at the same time share a thumbnail, so each is only listed once.
+fn missing(playlist: &Playlist) -> Result<BTreeMap<usize, Vec<(Time, PathBuf)>>> {
+    let mut missing = BTreeMap::<usize, Vec<(Time, PathBuf)>>::new();
+    for (cue, path) in paths(playlist)?.into_iter().enumerate() {
+        let (segment, time) = playlist.get(cue);
+        let frames = missing.entry(segment).or_default();
+        if !path.exists() && !frames.iter().any(|(_, x)| *x == path) {
+            frames.push((time, path));
+        }
+    }
+
+    missing.retain(|_, x| !x.is_empty());
+    Ok(missing)
+}
+
+/// Grabs the thumbnails of one file.
+fn save(media: &Path, fps: Option<f64>, frames: &[(Time, PathBuf)]) -> Result<()> {
+    // Each frame is saved under a temporary name first, so a half written file is never cached
+    let tmp = frames
+        .iter()
+        .map(|(time, path)| (*time, path.with_extension("tmp.jpg")))
+        .collect::<Vec<_>>();
+    if let Some(dir) = frames[0].1.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    MpvPlayer::grab_frames(media, fps, Some(WIDTH), &tmp)?;
+    for ((_, tmp), (_, path)) in tmp.iter().zip(frames) {
+        fs::rename(tmp, path)?;
+    }
+    Ok(())
+}
+
+/// The cache directory for the thumbnails of a file.
+fn media_dir(media: &Path) -> Result<PathBuf> {
+    Ok(cache_dir()
+        .join("thumbnails")
+        .join(format!("{:016x}", media_hash(media)?)))
+}
+
+/// The file name of the thumbnail at a time.
+fn file_name(time: Time) -> String {
+    format!("{}.jpg", time.to_string().replace(':', "-"))
+}
+
+/// Hashes the contents of a file.
+/// Hashing the whole of a long video would take a while, so only its length and the first and last [`SAMPLE`] bytes are used.
+fn media_hash(path: &Path) -> Result<u64> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    let mut data = len.to_le_bytes().to_vec();
+    file.by_ref().take(SAMPLE).read_to_end(&mut data)?;
+    file.seek(SeekFrom::Start(len.saturating_sub(SAMPLE).max(SAMPLE)))?;
+    file.take(SAMPLE).read_to_end(&mut data)?;
+
+    // FNV-1a, which unlike the std hasher is the same on every build
+    Ok(data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, x| {
+        (hash ^ *x as u64).wrapping_mul(0x0100_0000_01b3)
+    }))
+}
+
+/// Where cached files are kept, following each platform's convention.
+fn cache_dir() -> PathBuf {
+    env::var_os("XDG_CACHE_HOME")
+        .or_else(|| env::var_os("LOCALAPPDATA"))
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|x| PathBuf::from(x).join(".cache")))
+        .unwrap_or_else(env::temp_dir)
+        .join("video-presenter")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::{generate, media_dir, media_hash, missing, paths};
+    use crate::{
+        cues::Cues,
+        playlist::{Playlist, Segment},
+    };
+
+    /// Removes a directory from the real cache once a test is done, even if it fails.
+    struct Cleanup(PathBuf);
+
+    impl Drop for Cleanup {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_paths() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("a.mp4"), "video").unwrap();
+        fs::write(dir.join("b.mp4"), "video").unwrap();
+        fs::write(dir.join("c.mp4"), "another video").unwrap();
+
+        // Only the contents matter, not where the file is
+        let hash = |x: &str| media_hash(&dir.join(x)).unwrap();
+        assert_eq!(hash("a.mp4"), hash("b.mp4"));
+        assert_ne!(hash("a.mp4"), hash("c.mp4"));
+
+        let cues = ",,00:00:01:05,00:00:01:05,0,Cue Point\n".parse().unwrap();
+        let playlist = Playlist::from_segments(vec![
+            Segment {
+                media: dir.join("a.mp4"),
+                markers: None,
+                cues,
+                still: false,
+            },
+            Segment {
+                media: dir.join("slide.png"),
+                markers: None,
+                cues: Cues::still(),
+                still: true,
+            },
+        ]);
+
+        let paths = paths(&playlist).unwrap();
+        assert_eq!(paths.len(), 3);
+        assert!(paths[0].ends_with(format!("{:016x}/00-00-00-00.jpg", hash("a.mp4"))));
+        assert!(paths[1].ends_with(format!("{:016x}/00-00-01-05.jpg", hash("a.mp4"))));
+        assert_eq!(paths[2], dir.join("slide.png"));
+    }
+
+    #[test]
+    fn test_missing() {
+        // Contents no other video has, so nothing is cached for it yet
+        let tmp = tempfile::tempdir().unwrap();
+        let media = tmp.path().join("talk.mp4");
+        fs::write(&media, format!("video {}", tmp.path().display())).unwrap();
+        let _cleanup = Cleanup(media_dir(&media).unwrap());
+
+        // A marker at the very start shares its thumbnail with cue 0
+        let cues = "\
+            ,,00:00:00:00,00:00:00:00,0,Cue Point\n\
+            ,,00:00:01:00,00:00:01:00,0,Cue Point\n"
+            .parse()
+            .unwrap();
+        let playlist = Playlist::single(&media, None, cues);
+        let missing_frames = missing(&playlist).unwrap();
+        assert_eq!(missing_frames.len(), 1);
+        let frames = &missing_frames[&0];
+        assert_eq!(frames.len(), 2);
+        assert_ne!(frames[0].1, frames[1].1);
+
+        // Once cached, nothing is made again (so mpv is never started)
+        fs::create_dir_all(media_dir(&media).unwrap()).unwrap();
+        for (_, path) in frames {
+            fs::write(path, "jpeg").unwrap();
+        }
+        assert!(missing(&playlist).unwrap().is_empty());
+        generate(&playlist, None, || panic!("nothing should be made")).unwrap();
+    }
+}
@@ -4,6 +4,8 @@ use std::{
     time::Duration,
 };
 
+use crate::error::TimeError;
+
 /// A point in time in a video.
 /// (HH:MM:SS:FF)
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
@@ -53,12 +55,16 @@ impl Time {
 }
 
 impl FromStr for Time {
-    type Err = anyhow::Error;
+    type Err = TimeError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.trim().split(':').collect::<Vec<_>>();
+        if parts.len() > 4 {
+            return Err(TimeError::TooManyComponents(parts.len()));
+        }
+
         let mut time = [0; 4];
-        let parts = s.split(':');
-        for (i, part) in parts.rev().enumerate() {
+        for (i, part) in parts.iter().rev().enumerate() {
             time[3 - i] = part.parse()?;
         }
 
@@ -80,15 +86,24 @@ impl Display for Time {
     }
 }
 
+/// Formats a wall clock duration (not a point in the video) as `M:SS`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
 /// Macro to parse the "HH:MM:SS:FF" format into a Time at compile time
-pub macro time($hours:literal : $minutes:literal : $seconds:literal : $frames:literal) {
-    Time {
+pub macro time($hours:literal : $minutes:literal : $seconds:literal : $frames:literal) {{
+    // Each part is written with two digits, like the timecodes in cue files
+    #[allow(clippy::zero_prefixed_literal)]
+    let time = Time {
         hours: $hours,
         minutes: $minutes,
         seconds: $seconds,
         frames: $frames,
-    }
-}
+    };
+    time
+}}
 
 #[cfg(test)]
 mod tests {
@@ -139,6 +154,13 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_time_parse_invalid() {
+        assert!("".parse::<Time>().is_err());
+        assert!("00:xx:00:00".parse::<Time>().is_err());
+        assert!("00:00:00:00:00".parse::<Time>().is_err());
+    }
+
     #[test]
     fn test_time_macro() {
         assert_eq!(time!(00:00:12:00), Time::from_str("00:00:12:00").unwrap());
@@ -0,0 +1,174 @@
+//! The timeline overlay.
+//! While rehearsing it helps to see where you are at a glance, rather than waiting for the cue number to flash up after each jump.
+//! When shown (with [`Command::Timeline`](crate::state::Command::Timeline)), a bar along the bottom of the video marks every cue of the current file and how far through it playback is,
+//! along with the current cue number and name.
+
+use std::{fmt::Write, thread, time::Duration};
+
+use crate::{
+    player::{Player, OVERLAY_HEIGHT, OVERLAY_WIDTH},
+    playlist::Playlist,
+    presenter::Presenter,
+    session::Event,
+    state::{State, Status},
+    time::format_duration,
+};
+
+/// Id of the timeline's overlay, see [`Player::set_overlay`].
+pub const OVERLAY_ID: u32 = 1;
+/// How often the overlay is redrawn while shown.
+const TICK: Duration = Duration::from_millis(100);
+/// Space between the bar and the edges of the video.
+const MARGIN: u32 = 60;
+/// Height of the bar.
+const BAR: u32 = 8;
+/// How far the cue ticks stick out of the bar, above and below.
+const TICK_OVERHANG: u32 = 10;
+/// Font size of the labels above the bar.
+const FONT_SIZE: u32 = 34;
+
+/// Keeps the overlay up to date on a background thread, drawing it whenever the timeline is toggled on.
+/// It is hidden while the screen is blanked.
+pub fn start<P: Player + 'static>(presenter: &Presenter<P>) {
+    let presenter = presenter.clone();
+    thread::spawn(move || {
+        let mut last = None;
+        loop {
+            thread::sleep(TICK);
+            let Some(status) = presenter.status() else {
+                break;
+            };
+
+            let overlay = match status.timeline && !matches!(status.state, State::Blackout { .. }) {
+                true => match presenter.player().position() {
+                    // The duration isn't known for every file (like still images), it is left off if so
+                    Ok(position) => Some(render(
+                        &presenter.playlist(),
+                        &status,
+                        position,
+                        presenter.player().duration().unwrap_or(f64::NAN),
+                    )),
+                    Err(_) => continue,
+                },
+                false => None,
+            };
+            if overlay == last {
+                continue;
+            }
+
+            if let Err(e) = presenter
+                .player()
+                .set_overlay(OVERLAY_ID, overlay.as_deref())
+            {
+                presenter.log(Event::Error {
+                    message: e.to_string(),
+                });
+            }
+            last = overlay;
+        }
+    });
+}
+
+/// Builds the ASS events for the timeline of the current file.
+/// `position` and `duration` are in seconds, a `duration` that isn't positive leaves out the progress and cue ticks.
+pub fn render(playlist: &Playlist, status: &Status, position: f64, duration: f64) -> String {
+    let width = OVERLAY_WIDTH - MARGIN * 2;
+    let top = OVERLAY_HEIGHT - MARGIN - BAR;
+    let x = |secs: f64| MARGIN + (secs / duration * width as f64).clamp(0.0, width as f64) as u32;
+
+    let mut out = rect(MARGIN, top, width, BAR, "000000", "60");
+    if duration > 0.0 {
+        out += &rect(MARGIN, top, x(position) - MARGIN, BAR, "FFFFFF", "20");
+        for cue in playlist.segments()[status.segment].cues.iter() {
+            let x = x(cue.as_secs(status.fps)).saturating_sub(2);
+            let height = BAR + TICK_OVERHANG * 2;
+            out += &rect(x, top - TICK_OVERHANG, 4, height, "00D7FF", "00");
+        }
+    }
+
+    let mut label = format!("#{}/{}", status.cue, playlist.len());
+    if let Some(name) = playlist.name(status.cue) {
+        let _ = write!(label, " \u{2013} {name}");
+    }
+    let baseline = top - TICK_OVERHANG - 8;
+    out += &text(1, MARGIN, baseline, &label);
+
+    if duration > 0.0 && duration.is_finite() {
+        let time = format!(
+            "{} / {}",
+            format_duration(Duration::from_secs_f64(position.max(0.0))),
+            format_duration(Duration::from_secs_f64(duration))
+        );
+        out += &text(3, OVERLAY_WIDTH - MARGIN, baseline, &time);
+    }
+
+    out
+}
+
+/// A filled rectangle, with the colour as BGR hex and the alpha as hex (`00` is opaque).
+pub(crate) fn rect(x: u32, y: u32, width: u32, height: u32, colour: &str, alpha: &str) -> String {
+    let (right, bottom) = (x + width, y + height);
+    format!(
+        "{{\\an7\\pos(0,0)\\bord0\\shad0\\1c&H{colour}&\\1a&H{alpha}&\\p1}}\
+         m {x} {y} l {right} {y} {right} {bottom} {x} {bottom}{{\\p0}}\n"
+    )
+}
+
+/// A line of outlined text, anchored with the numpad style `align` (1 is bottom left, 3 bottom right).
+pub(crate) fn text(align: u8, x: u32, y: u32, text: &str) -> String {
+    format!(
+        "{{\\an{align}\\pos({x},{y})\\fs{FONT_SIZE}\\bord2\\1c&HFFFFFF&}}{}\n",
+        escape(text)
+    )
+}
+
+/// Escapes text so it can't be read as ASS tags.
+fn escape(text: &str) -> String {
+    // A zero width space after a backslash stops it starting an escape
+    text.replace('\\', "\\\u{200b}")
+        .replace('{', "\\{")
+        .replace('}', "\\}")
+        .replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::{
+        playlist::Playlist,
+        state::{State, Status},
+    };
+
+    #[test]
+    fn test_render() {
+        let cues = "\
+            ,,00:00:01:00,00:00:01:00,0,Cue Point\n\
+            Demo {1},,00:00:02:05,00:00:02:05,0,Cue Point\n"
+            .parse()
+            .unwrap();
+        let playlist = Playlist::single("test.mp4", None, cues);
+        let status = Status {
+            state: State::Held { cue: 2 },
+            cue: 2,
+            segment: 0,
+            fps: 10.0,
+            timeline: true,
+            speed: 1.0,
+        };
+
+        // The background, the progress, a tick per cue, then the labels
+        let lines = render(&playlist, &status, 2.5, 5.0)
+            .lines()
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[1].ends_with("m 60 1012 l 960 1012 960 1020 60 1020{\\p0}"));
+        assert!(lines[2].contains("m 418 1002 "));
+        assert!(lines[4].ends_with("}#2/2 \u{2013} Demo \\{1\\}"));
+        assert!(lines[5].ends_with("}0:02 / 0:05"));
+
+        // Without a duration there is only the bar and cue number
+        let lines = render(&playlist, &status, 0.0, f64::NAN);
+        assert_eq!(lines.lines().count(), 2);
+    }
+}
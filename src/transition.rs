@@ -0,0 +1,166 @@
+//! Transitions for jumps.
+//! Seeking to a cue that isn't the next one hard cuts to the new frame, which looks jarring on a big screen.
+//! A transition hides the cut, either by fading through black or by crossfading from a still of the old frame.
+//! Only jumps ([`Command::Next`](crate::state::Command::Next), `Back` and `Goto`) get a transition, playing into a cue never does.
+
+use std::{env, path::PathBuf, process, thread, time::Duration};
+
+use serde::Deserialize;
+
+use crate::{
+    error::Result,
+    player::{Cover, Player},
+};
+
+/// How often the cover is updated while transitioning.
+const TICK: Duration = Duration::from_millis(40);
+
+/// The kinds of transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionKind {
+    /// Fade out to black, jump, then fade back in.
+    Fade,
+    /// Jump straight away, fading out a still of the old frame over the new one.
+    Crossfade,
+}
+
+/// A transition, and how long it takes.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    /// Length of the whole transition in seconds.
+    #[serde(default = "Transition::default_duration")]
+    pub duration: f64,
+}
+
+impl Transition {
+    pub const fn default_duration() -> f64 {
+        0.5
+    }
+
+    /// Runs `jump` behind the transition, blocking until it has finished.
+    /// The cover is always removed afterwards, even if the jump fails.
+    pub fn run<P: Player + ?Sized>(
+        &self,
+        player: &P,
+        jump: impl FnOnce() -> Result<()>,
+    ) -> Result<()> {
+        let duration = Duration::from_secs_f64(self.duration.max(0.0));
+        // Failing to draw the cover doesn't stop the jump, it just isn't hidden
+        let (covered, res, faded) = match self.kind {
+            TransitionKind::Fade => {
+                let half = duration / 2;
+                let covered = fade(player, half, Cover::Black);
+                let res = jump();
+                let faded = fade(player, half, |x| Cover::Black(1.0 - x));
+                (covered, res, faded)
+            }
+            TransitionKind::Crossfade => {
+                let still = still_path();
+                let covered = player
+                    .capture(&still)
+                    .and_then(|()| player.set_cover(Some(&Cover::Still(still.clone(), 1.0))));
+                let res = jump();
+                let faded = match covered {
+                    Ok(()) => fade(player, duration, |x| Cover::Still(still.clone(), 1.0 - x)),
+                    Err(_) => Ok(()),
+                };
+                (covered, res, faded)
+            }
+        };
+
+        // Whatever went wrong, the cover must never be left over the video
+        let uncovered = player.set_cover(None);
+        covered.and(res).and(faded).and(uncovered)
+    }
+}
+
+/// Steps the cover through a fade, calling `cover` with the progress from 0 to 1.
+fn fade<P: Player + ?Sized>(
+    player: &P,
+    duration: Duration,
+    cover: impl Fn(f64) -> Cover,
+) -> Result<()> {
+    let steps = (duration.as_secs_f64() / TICK.as_secs_f64())
+        .ceil()
+        .max(1.0) as u32;
+    for i in 1..=steps {
+        player.set_cover(Some(&cover(i as f64 / steps as f64)))?;
+        thread::sleep(duration / steps);
+    }
+    Ok(())
+}
+
+/// Where the still of the old frame is saved for a crossfade.
+fn still_path() -> PathBuf {
+    env::temp_dir().join(format!("video-presenter-still-{}.png", process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::{Transition, TransitionKind};
+    use crate::player::{sim::SimPlayer, Cover, Player};
+
+    #[test]
+    fn test_fade() {
+        let player = SimPlayer::new(10, 100);
+        let transition = Transition {
+            kind: TransitionKind::Fade,
+            duration: 0.16,
+        };
+
+        // The jump happens while the screen is fully black
+        let covered = Cell::new(None);
+        transition
+            .run(&player, || {
+                covered.set(player.covers().last().cloned());
+                player.seek(5.0)
+            })
+            .unwrap();
+
+        assert_eq!(covered.take(), Some(Some(Cover::Black(1.0))));
+        assert_eq!(player.covers().first(), Some(&Some(Cover::Black(0.5))));
+        assert_eq!(player.covers().last(), Some(&None));
+        assert_eq!(player.frame(), 50);
+    }
+
+    #[test]
+    fn test_crossfade() {
+        let player = SimPlayer::new(10, 100);
+        let transition = Transition {
+            kind: TransitionKind::Crossfade,
+            duration: 0.1,
+        };
+        transition.run(&player, || Ok(())).unwrap();
+
+        let covers = player.covers();
+        let Some(Cover::Still(still, 1.0)) = &covers[0] else {
+            panic!("expected the still first, got {covers:?}");
+        };
+        assert_eq!(player.captured().as_ref(), Some(still));
+        assert_eq!(
+            covers[covers.len() - 2],
+            Some(Cover::Still(still.clone(), 0.0))
+        );
+        assert_eq!(covers.last(), Some(&None));
+    }
+
+    #[test]
+    fn test_failed_capture_removes_cover() {
+        let player = SimPlayer::new(10, 100);
+        player.fail_captures();
+        let transition = Transition {
+            kind: TransitionKind::Crossfade,
+            duration: 0.1,
+        };
+
+        // The jump still happens, and the error is passed on
+        assert!(transition.run(&player, || player.seek(5.0)).is_err());
+        assert_eq!(player.frame(), 50);
+        assert_eq!(player.covers().last(), Some(&None));
+    }
+}